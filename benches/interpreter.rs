@@ -0,0 +1,125 @@
+//! Criterion benchmarks for the tokenize, read and eval stages, over a large
+//! flat program, a deeply nested one and a numeric one, plus a couple of
+//! programs that pass a big vector around.
+
+use std::hint::black_box;
+use std::io;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use warp::{eval, AST, ENV};
+
+/// Lots of small top level forms: defs, calls and collection literals.
+fn flat_program(forms: usize) -> String {
+    let mut src = String::new();
+    for i in 0..forms {
+        src.push_str(&format!(
+            "(def x{} (+ {} (* 2 {}) (- 10 {})))\n[{} {} :k{}]\n{{:a {} :b \"s{}\"}}\n",
+            i, i, i, i, i, i, i, i, i
+        ));
+    }
+    src
+}
+
+/// Nothing but numbers, where reading shouldn't need to allocate per atom.
+fn numeric_program(forms: usize) -> String {
+    let mut src = String::new();
+    for i in 0..forms {
+        src.push_str(&format!("[{} {}.5 -{} 0x{:x}]\n", i, i, i, i));
+    }
+    src
+}
+
+/// A single form nested `depth` levels deep.
+fn nested_program(depth: usize) -> String {
+    let mut src = String::new();
+    for _ in 0..depth {
+        src.push_str("(+ 1 ");
+    }
+    src.push('0');
+    for _ in 0..depth {
+        src.push(')');
+    }
+    src
+}
+
+/// Defines a vector of `len` numbers and a chain of functions that each hand
+/// it to the next, so every call binds the whole vector.
+fn big_vector_program(len: usize) -> String {
+    let items: Vec<String> = (0..len).map(|i| i.to_string()).collect();
+    format!(
+        "(def big [{}])\n\
+         (defn last-step [v] (count v))\n\
+         (defn third-step [v] (last-step v))\n\
+         (defn second-step [v] (third-step v))\n\
+         (defn first-step [v] (second-step v))\n",
+        items.join(" ")
+    )
+}
+
+/// Conjes `times` numbers onto `big`, each onto the result of the last.
+fn conj_program(times: usize) -> String {
+    let mut src = "big".to_string();
+    for i in 0..times {
+        src = format!("(conj {} {})", src, i);
+    }
+    format!("(count {})\n", src)
+}
+
+fn read(src: &str) -> AST {
+    AST::read(&AST::tokenize(src.to_string()).unwrap()).unwrap()
+}
+
+fn programs() -> Vec<(&'static str, String)> {
+    vec![
+        ("flat", flat_program(2_000)),
+        ("nested", nested_program(500)),
+        ("numeric", numeric_program(2_000)),
+    ]
+}
+
+fn tokenize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tokenize");
+    for (label, src) in programs() {
+        group.bench_function(label, |b| {
+            b.iter(|| AST::tokenize(black_box(src.clone())).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn read_tokens(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read");
+    for (label, src) in programs() {
+        let tokens = AST::tokenize(src).unwrap();
+        group.bench_function(label, |b| b.iter(|| AST::read(black_box(&tokens)).unwrap()));
+    }
+    group.finish();
+}
+
+fn eval_ast(c: &mut Criterion) {
+    let mut group = c.benchmark_group("eval");
+    for (label, src) in programs() {
+        let ast = read(&src);
+        group.bench_function(label, |b| {
+            b.iter(|| {
+                let mut env = ENV::with_output(Box::new(io::sink()));
+                eval(black_box(&ast), &mut env).unwrap()
+            })
+        });
+    }
+
+    let mut env = ENV::with_output(Box::new(io::sink()));
+    eval(&read(&big_vector_program(100_000)), &mut env).unwrap();
+    let calls = read(&"(first-step big)\n".repeat(100));
+    group.bench_function("calls big vector", |b| {
+        b.iter(|| eval(black_box(&calls), &mut env).unwrap())
+    });
+    let conjes = read(&conj_program(100));
+    group.bench_function("conj big vector", |b| {
+        b.iter(|| eval(black_box(&conjes), &mut env).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, tokenize, read_tokens, eval_ast);
+criterion_main!(benches);
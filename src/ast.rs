@@ -0,0 +1,1251 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use std::rc::Rc;
+
+use crate::atom::Atom;
+use crate::regex::Regex;
+
+/// A piece of source text and where it starts. Every position in the reader,
+/// here and in errors and `AST::source_map`, is a 1-based line and a 1-based
+/// column counted in characters, not bytes, like `count` and `subs` count
+/// strings: `λ` is one column.
+#[derive(Debug)]
+pub struct Token {
+    pub value: String,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Token {
+    pub fn new(value: String, line: usize, col: usize) -> Token {
+        Token { value, line, col }
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+/// The reader macros a `#` at the start of a token dispatches to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dispatch {
+    /// `#{...}`, a set literal.
+    Set,
+    /// `#(...)`, a function literal.
+    Fn,
+    /// `#_`, which makes the reader skip the next form.
+    Discard,
+    /// `#"..."`, a regex literal.
+    Regex,
+    /// `#tag form`, a tagged literal: the form is handed to the handler
+    /// registered for the tag with `Reader::register_tag`.
+    Tag,
+    /// `#?(:warp x :default y)`, a reader conditional: only the form for
+    /// the reader's platform, or the default, is read.
+    Conditional,
+}
+
+impl Dispatch {
+    /// The dispatch for the character after a `#`.
+    fn from_char(c: char) -> Option<Dispatch> {
+        match c {
+            '{' => Some(Dispatch::Set),
+            '(' => Some(Dispatch::Fn),
+            '_' => Some(Dispatch::Discard),
+            '"' => Some(Dispatch::Regex),
+            '?' => Some(Dispatch::Conditional),
+            c if c.is_alphabetic() => Some(Dispatch::Tag),
+            _ => None,
+        }
+    }
+
+    /// The token the tokenizer makes for this dispatch, or how it starts for
+    /// a tag, which goes on with the tag's name.
+    pub fn token(self) -> &'static str {
+        match self {
+            Dispatch::Set => "#{",
+            Dispatch::Fn => "#(",
+            Dispatch::Discard => "#_",
+            Dispatch::Regex => "#\"",
+            Dispatch::Tag => "#",
+            Dispatch::Conditional => "#?(",
+        }
+    }
+}
+
+impl Token {
+    /// The reader macro this token dispatches to, if it's a `#` token.
+    pub fn dispatch(&self) -> Option<Dispatch> {
+        let mut chars = self.value.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            // the whole literal is one token
+            (Some('#'), Some('"'), _) => Some(Dispatch::Regex),
+            (Some('#'), Some('?'), Some('(')) => Some(Dispatch::Conditional),
+            (Some('#'), Some(c), None) => Dispatch::from_char(c),
+            (Some('#'), Some(c), Some(_)) if c.is_alphabetic() => Some(Dispatch::Tag),
+            _ => None,
+        }
+    }
+}
+
+/// The characters that may follow a `\` inside a string literal.
+pub const ESCAPES: &[char] = &['"', '\\', 'n', 't', 'r', '0'];
+
+#[derive(Debug, PartialEq)]
+pub enum LexError {
+    /// End of input inside a string literal, positioned at its opening quote.
+    UnterminatedString { line: usize, col: usize },
+    /// A `\` followed by a character that isn't in `ESCAPES`.
+    InvalidEscape {
+        escape: char,
+        line: usize,
+        col: usize,
+    },
+    /// A token that starts like a number but isn't one, like `1abc`.
+    InvalidNumber {
+        token: String,
+        line: usize,
+        col: usize,
+    },
+    /// A `\` and a name that isn't a character, like `\foo`.
+    InvalidCharacter {
+        token: String,
+        line: usize,
+        col: usize,
+    },
+    /// A `#` at the start of a token followed by something that isn't a
+    /// `Dispatch`, or by nothing at all.
+    UnknownDispatch {
+        found: Option<char>,
+        line: usize,
+        col: usize,
+    },
+}
+
+impl LexError {
+    /// Where the error is, as `(line, col)`.
+    pub fn position(&self) -> (usize, usize) {
+        match self {
+            LexError::UnterminatedString { line, col }
+            | LexError::InvalidEscape { line, col, .. }
+            | LexError::InvalidNumber { line, col, .. }
+            | LexError::InvalidCharacter { line, col, .. }
+            | LexError::UnknownDispatch { line, col, .. } => (*line, *col),
+        }
+    }
+
+    /// What went wrong, without the position.
+    pub fn message(&self) -> String {
+        match self {
+            LexError::UnterminatedString { .. } => "unterminated string".to_string(),
+            LexError::InvalidEscape { escape, .. } => format!("invalid escape '\\{}'", escape),
+            LexError::InvalidNumber { token, .. } => format!("invalid number: {}", token),
+            LexError::InvalidCharacter { token, .. } => format!("invalid character: {}", token),
+            LexError::UnknownDispatch { found, .. } => match found {
+                Some(c) => format!("no dispatch macro for '#{}'", c.escape_debug()),
+                None => "'#' at end of input".to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (line, col) = self.position();
+        write!(f, "{}:{}: {}", line, col, self.message())
+    }
+}
+
+/// A character iterator that keeps track of the 1-based line and column of
+/// each character it yields. A multibyte character moves the column by one.
+struct Chars<'a> {
+    chars: std::str::Chars<'a>,
+    line: usize,
+    col: usize,
+}
+
+impl<'a> Chars<'a> {
+    fn new(src: &'a str) -> Chars<'a> {
+        Chars {
+            chars: src.chars(),
+            line: 1,
+            col: 1,
+        }
+    }
+}
+
+impl<'a> Chars<'a> {
+    fn peek(&self) -> Option<char> {
+        self.chars.clone().next()
+    }
+}
+
+impl<'a> Iterator for Chars<'a> {
+    type Item = (char, usize, usize);
+
+    fn next(&mut self) -> Option<(char, usize, usize)> {
+        let c = self.chars.next()?;
+        let position = (c, self.line, self.col);
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+        Some(position)
+    }
+}
+
+#[derive(Debug)]
+pub struct SExp {
+    pub _type: &'static str,
+    pub children: Vec<Atom>,
+    /// Where the token that opened the form is.
+    pub line: usize,
+    pub col: usize,
+}
+
+impl SExp {
+    /// The form a delimiter opens. Strings aren't forms: the tokenizer reads
+    /// a whole literal as one token that becomes an `Atom::String`. `'` isn't
+    /// a delimiter either but a reader macro, see `SExp::prefix`.
+    fn new(t: &Token) -> SExp {
+        let _type = match t.value.as_str() {
+            "(" => "exec",
+            "[" => "vec",
+            "{" => "map",
+            "#{" => "set",
+            "#(" => "fn",
+            "#?(" => "conditional",
+            _ => panic!("Unsupported type"),
+        };
+
+        SExp::prefix(_type, t)
+    }
+
+    /// A form opened by a reader macro like `'` that wraps the next form
+    /// rather than waiting for a closing delimiter.
+    fn prefix(_type: &'static str, t: &Token) -> SExp {
+        SExp {
+            _type,
+            children: vec![],
+            line: t.line,
+            col: t.col,
+        }
+    }
+
+    fn is_prefix(&self) -> bool {
+        matches!(
+            self._type,
+            "quote" | "deref" | "syntax-quote" | "unquote" | "unquote-splicing" | "discard" | "tag"
+        )
+    }
+
+    fn push(&mut self, atom: Atom) {
+        self.children.push(atom);
+    }
+
+    /// The delimiter that opens this form.
+    fn opener(&self) -> &'static str {
+        match self._type {
+            "vec" => "[",
+            "map" => "{",
+            "set" => "#{",
+            "fn" => "#(",
+            "conditional" => "#?(",
+            "quote" => "'",
+            "deref" => "@",
+            "syntax-quote" => "`",
+            "unquote" => "~",
+            "unquote-splicing" => "~@",
+            _ => "(",
+        }
+    }
+
+    /// The delimiter that closes this form.
+    fn closer(&self) -> &'static str {
+        match self._type {
+            "vec" => "]",
+            "map" | "set" => "}",
+            "quote" | "deref" | "syntax-quote" | "unquote" | "unquote-splicing" => "",
+            _ => ")",
+        }
+    }
+}
+
+impl fmt::Display for SExp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let output = format!("({}", self._type);
+        let mut body: Vec<String> = vec![];
+        for child in &self.children {
+            body.push(child.to_string());
+        }
+        let body = body.join(" ");
+        let output = format!("{} {})", output, body);
+
+        write!(f, "{}", output)
+    }
+}
+
+/// A borrowed view of one form in an `AST`, so consumers can walk nested
+/// forms without looking each `Atom::Reference` up by hand.
+#[derive(Debug, Clone, Copy)]
+pub struct FormRef<'a> {
+    pub ast: &'a AST,
+    pub id: usize,
+}
+
+/// A child of a form: either another form or a plain atom.
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'a> {
+    Form(FormRef<'a>),
+    Atom(&'a Atom),
+}
+
+impl<'a> FormRef<'a> {
+    pub fn sexp(&self) -> &'a SExp {
+        &self.ast.items[&self.id]
+    }
+
+    pub fn kind(&self) -> &'static str {
+        self.sexp()._type
+    }
+
+    pub fn len(&self) -> usize {
+        self.sexp().children.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sexp().children.is_empty()
+    }
+
+    /// The children of this form with references already resolved.
+    pub fn children(&self) -> impl Iterator<Item = Node<'a>> + 'a {
+        let ast = self.ast;
+        self.sexp()
+            .children
+            .iter()
+            .map(move |child| ast.node(child))
+    }
+}
+
+impl<'a> fmt::Display for FormRef<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sexp = self.sexp();
+        write!(f, "{}", sexp.opener())?;
+        for (i, child) in self.children().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", child)?;
+        }
+        write!(f, "{}", sexp.closer())
+    }
+}
+
+impl<'a> fmt::Display for Node<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Node::Form(form) => write!(f, "{}", form),
+            // a tagged literal can leave a whole collection here
+            Node::Atom(atom) => write!(f, "{}", atom.readable()),
+        }
+    }
+}
+
+/// An owned form, for tools that would rather have a plain tree than an
+/// `AST` full of ids. See `AST::read_tree`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Form {
+    Atom(Atom),
+    List(Vec<Form>),
+    Vector(Vec<Form>),
+    /// Entries in source order.
+    Map(Vec<(Form, Form)>),
+    Set(Vec<Form>),
+    /// The body of `#(...)`, before it's expanded into a `fn`.
+    FnLiteral(Vec<Form>),
+    Quote(Box<Form>),
+    Deref(Box<Form>),
+    SyntaxQuote(Box<Form>),
+    Unquote(Box<Form>),
+    UnquoteSplicing(Box<Form>),
+}
+
+impl<'a> Node<'a> {
+    /// Copies this node and everything in it into an owned `Form`.
+    pub fn to_form(&self) -> Form {
+        let form = match self {
+            Node::Form(form) => form,
+            Node::Atom(atom) => return Form::Atom((*atom).clone()),
+        };
+        let mut children: Vec<Form> = form.children().map(|child| child.to_form()).collect();
+        match form.kind() {
+            "vec" => Form::Vector(children),
+            "map" => {
+                let mut entries = vec![];
+                let mut children = children.into_iter();
+                while let (Some(key), Some(value)) = (children.next(), children.next()) {
+                    entries.push((key, value));
+                }
+                Form::Map(entries)
+            }
+            "set" => Form::Set(children),
+            "fn" => Form::FnLiteral(children),
+            "quote" => Form::Quote(Box::new(children.remove(0))),
+            "deref" => Form::Deref(Box::new(children.remove(0))),
+            "syntax-quote" => Form::SyntaxQuote(Box::new(children.remove(0))),
+            "unquote" => Form::Unquote(Box::new(children.remove(0))),
+            "unquote-splicing" => Form::UnquoteSplicing(Box::new(children.remove(0))),
+            _ => Form::List(children),
+        }
+    }
+}
+
+/// Renders the form back as source, the same way `Node` does.
+impl fmt::Display for Form {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn seq(f: &mut fmt::Formatter, open: &str, items: &[Form], close: &str) -> fmt::Result {
+            write!(f, "{}", open)?;
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " ")?;
+                }
+                write!(f, "{}", item)?;
+            }
+            write!(f, "{}", close)
+        }
+        match self {
+            Form::Atom(atom) => write!(f, "{}", atom.readable()),
+            Form::List(items) => seq(f, "(", items, ")"),
+            Form::Vector(items) => seq(f, "[", items, "]"),
+            Form::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{} {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+            Form::Set(items) => seq(f, "#{", items, "}"),
+            Form::FnLiteral(items) => seq(f, "#(", items, ")"),
+            Form::Quote(form) => write!(f, "'{}", form),
+            Form::Deref(form) => write!(f, "@{}", form),
+            Form::SyntaxQuote(form) => write!(f, "`{}", form),
+            Form::Unquote(form) => write!(f, "~{}", form),
+            Form::UnquoteSplicing(form) => write!(f, "~@{}", form),
+        }
+    }
+}
+
+/// A reader error, positioned at the token that caused it.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// The text of the offending token.
+    pub token: String,
+    pub line: usize,
+    pub col: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseErrorKind {
+    /// A closing delimiter with no form left to close.
+    UnexpectedClose,
+    /// A closing delimiter that doesn't match the innermost open form.
+    Mismatched { expected: &'static str },
+    /// Input ended with this many forms still open. The token is the opener
+    /// of the innermost one.
+    Unclosed(usize),
+    /// A map literal with a key but no value. The token is its `{`.
+    OddMap,
+    /// The same key twice in a map literal, or the same element twice in a
+    /// set literal. The token is the literal's opener.
+    DuplicateKey(String),
+    /// A `#(...)` inside another one, which would make `%` ambiguous.
+    NestedFnLiteral,
+    /// A regex literal that doesn't compile.
+    InvalidRegex(String),
+    /// A tagged literal whose tag has no handler. The token is the tag.
+    UnknownTag,
+    /// A tagged literal whose handler rejected the form, with its message.
+    /// The token is the tag.
+    InvalidTagged(String),
+    /// A reader conditional that isn't pairs of a keyword and a form. The
+    /// token is its `#?(`.
+    InvalidConditional(String),
+}
+
+impl ParseError {
+    fn new(kind: ParseErrorKind, token: &Token) -> ParseError {
+        ParseError {
+            kind,
+            token: token.value.clone(),
+            line: token.line,
+            col: token.col,
+        }
+    }
+
+    /// What went wrong, without the position.
+    pub fn message(&self) -> String {
+        let token = &self.token;
+        match &self.kind {
+            ParseErrorKind::UnexpectedClose => format!("unexpected '{}'", token),
+            ParseErrorKind::Mismatched { expected } => {
+                format!("expected '{}' but found '{}'", expected, token)
+            }
+            ParseErrorKind::Unclosed(count) => format!(
+                "'{}' is never closed ({} unclosed form(s) at end of input)",
+                token, count
+            ),
+            ParseErrorKind::OddMap => format!(
+                "map literal '{}' must contain an even number of forms",
+                token
+            ),
+            ParseErrorKind::DuplicateKey(key) => {
+                format!("duplicate key {} in '{}' literal", key, token)
+            }
+            ParseErrorKind::InvalidRegex(message) => {
+                format!("invalid regex {}: {}", token, message)
+            }
+            ParseErrorKind::NestedFnLiteral => {
+                format!("nested '{}' inside another #() is not allowed", token)
+            }
+            ParseErrorKind::UnknownTag => format!("no reader for tag {}", token),
+            ParseErrorKind::InvalidTagged(message) => format!("{}: {}", token, message),
+            ParseErrorKind::InvalidConditional(message) => {
+                format!("invalid reader conditional '{}': {}", token, message)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.col, self.message())
+    }
+}
+
+#[derive(Debug)]
+pub struct AST {
+    /// Every form, by id. `read` gives each form its own id and exactly one
+    /// parent, so the ids describe a tree. An `AST` built by hand can share
+    /// a form between parents; `form` resolves it once.
+    pub items: HashMap<usize, SExp>,
+    /// The top level forms, in source order.
+    pub roots: Vec<Atom>,
+    /// Where each of `roots` starts, as `(line, col)`. Plain atoms at the
+    /// top level have no `SExp` to hold their position, so it's kept here.
+    pub positions: Vec<(usize, usize)>,
+    /// How many tokens the program was read from.
+    pub tokens: usize,
+}
+
+/// Renders the program back as source, one top level form per line.
+impl fmt::Display for AST {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for form in self.forms() {
+            writeln!(f, "{}", form)?;
+        }
+        Ok(())
+    }
+}
+
+impl AST {
+    /// The form with the given id, if there is one.
+    pub fn get(&self, id: usize) -> Option<FormRef<'_>> {
+        if self.items.contains_key(&id) {
+            Some(FormRef { ast: self, id })
+        } else {
+            None
+        }
+    }
+
+    /// Resolves `atom` into a `Node`, following it if it's a reference.
+    pub fn node<'a>(&'a self, atom: &'a Atom) -> Node<'a> {
+        match atom {
+            Atom::Reference(id) => Node::Form(FormRef { ast: self, id: *id }),
+            atom => Node::Atom(atom),
+        }
+    }
+
+    /// The top level forms, in source order.
+    pub fn forms(&self) -> impl Iterator<Item = Node<'_>> {
+        self.roots.iter().map(move |root| self.node(root))
+    }
+
+    /// Calls `f` with every form in the tree in source order, each one
+    /// before the forms inside it. For `(a (b (c)))` that's three calls.
+    pub fn walk<F: FnMut(&SExp)>(&self, mut f: F) {
+        self.visit(|node| {
+            if let Node::Form(form) = node {
+                f(form.sexp());
+            }
+        });
+    }
+
+    /// Like `walk`, but `f` sees every node: the forms and the plain atoms
+    /// in them.
+    pub fn visit<'a, F: FnMut(Node<'a>)>(&'a self, mut f: F) {
+        for root in self.forms() {
+            visit_node(root, &mut f);
+        }
+    }
+
+    pub fn tokenize(src: String) -> Result<Vec<Token>, LexError> {
+        let mut tokens: Vec<Token> = vec![];
+        let mut chars = Chars::new(&src);
+        // the symbol/number currently being built up
+        let mut word: Option<Token> = None;
+
+        while let Some((c, line, col)) = chars.next() {
+            match c {
+                // commas are whitespace, like clojure, so printed maps read back
+                ' ' | ',' | '\n' | '\t' | '\r' => finish_word(&mut tokens, word.take())?,
+                // like `#`, a quote only starts a reader macro at the start of a
+                // token; inside one it's part of the name, as in `x'`
+                '\'' if word.is_some() => word.as_mut().unwrap().value.push(c),
+                '~' if chars.peek() == Some('@') => {
+                    finish_word(&mut tokens, word.take())?;
+                    chars.next();
+                    tokens.push(Token::new("~@".to_string(), line, col));
+                }
+                '{' | '}' | '[' | ']' | '(' | ')' | '\'' | '@' | '~' | '`' => {
+                    finish_word(&mut tokens, word.take())?;
+                    tokens.push(Token::new(c.to_string(), line, col));
+                }
+                '"' => {
+                    finish_word(&mut tokens, word.take())?;
+                    tokens.push(AST::tokenize_string(&mut chars, line, col, false)?);
+                }
+                // a character literal: whatever follows the `\` is part of
+                // it, even a delimiter, as in `\(` or `\"`
+                '\\' if word.is_none() => {
+                    let mut token = Token::new(c.to_string(), line, col);
+                    if let Some((next, _, _)) = chars.next() {
+                        token.value.push(next);
+                    }
+                    word = Some(token);
+                }
+                '#' if word.is_none() => match chars.peek().and_then(Dispatch::from_char) {
+                    Some(Dispatch::Regex) => {
+                        chars.next();
+                        tokens.push(AST::tokenize_string(&mut chars, line, col, true)?);
+                    }
+                    // the tag's name is read like any other word
+                    Some(Dispatch::Tag) => word = Some(Token::new("#".to_string(), line, col)),
+                    // `#?` only ever opens a list
+                    Some(Dispatch::Conditional) => {
+                        chars.next();
+                        if chars.peek() != Some('(') {
+                            return Err(LexError::UnknownDispatch {
+                                found: Some('?'),
+                                line,
+                                col,
+                            });
+                        }
+                        chars.next();
+                        tokens.push(Token::new("#?(".to_string(), line, col));
+                    }
+                    Some(dispatch) => {
+                        chars.next();
+                        tokens.push(Token::new(dispatch.token().to_string(), line, col));
+                    }
+                    None => {
+                        return Err(LexError::UnknownDispatch {
+                            found: chars.peek(),
+                            line,
+                            col,
+                        })
+                    }
+                },
+                _ => match &mut word {
+                    Some(token) => token.value.push(c),
+                    None => word = Some(Token::new(c.to_string(), line, col)),
+                },
+            }
+        }
+        finish_word(&mut tokens, word)?;
+
+        Ok(tokens)
+    }
+
+    /// Reads the rest of a string literal whose opening quote was at
+    /// `line`/`col`. The token keeps the quotes and escapes as written;
+    /// `Atom::infer` decodes them. The body of a regex literal is kept for
+    /// the regex to interpret, so any escape goes.
+    fn tokenize_string(
+        chars: &mut Chars,
+        line: usize,
+        col: usize,
+        regex: bool,
+    ) -> Result<Token, LexError> {
+        let mut value = String::from(if regex { "#\"" } else { "\"" });
+        loop {
+            match chars.next() {
+                Some(('"', _, _)) => {
+                    value.push('"');
+                    return Ok(Token::new(value, line, col));
+                }
+                Some(('\\', escape_line, escape_col)) => match chars.next() {
+                    Some((c, _, _)) if regex || ESCAPES.contains(&c) => {
+                        value.push('\\');
+                        value.push(c);
+                    }
+                    Some((c, _, _)) => {
+                        return Err(LexError::InvalidEscape {
+                            escape: c,
+                            line: escape_line,
+                            col: escape_col,
+                        })
+                    }
+                    None => return Err(LexError::UnterminatedString { line, col }),
+                },
+                Some((c, _, _)) => value.push(c),
+                None => return Err(LexError::UnterminatedString { line, col }),
+            }
+        }
+    }
+
+    /// Reads `tokens` with a `Reader` that knows no tags.
+    pub fn read(tokens: &[Token]) -> Result<AST, ParseError> {
+        Reader::new().read(tokens)
+    }
+
+    /// How many forms are still open at the end of `tokens`, checking only
+    /// the delimiters: 0 means everything is closed. A closing delimiter too
+    /// many, or of the wrong kind, is the same error `read` gives. Cheaper
+    /// than `read` when all that matters is whether more input is needed,
+    /// like at a REPL prompt.
+    pub fn open_forms(tokens: &[Token]) -> Result<usize, ParseError> {
+        // the closer each open form is waiting for, "" for a prefix like `'`
+        // that's waiting for any one form
+        let mut open: Vec<&'static str> = vec![];
+        for token in tokens {
+            match token.value.as_str() {
+                "'" | "@" | "`" | "~" | "~@" | "#_" => {
+                    open.push("");
+                    continue;
+                }
+                _ if token.dispatch() == Some(Dispatch::Tag) => {
+                    open.push("");
+                    continue;
+                }
+                "(" | "[" | "{" | "#{" | "#(" | "#?(" => {
+                    open.push(SExp::new(token).closer());
+                    continue;
+                }
+                ")" | "]" | "}" => match open.pop() {
+                    Some(closer) if closer == token.value => (),
+                    Some("") | None => {
+                        return Err(ParseError::new(ParseErrorKind::UnexpectedClose, token))
+                    }
+                    Some(expected) => {
+                        let kind = ParseErrorKind::Mismatched { expected };
+                        return Err(ParseError::new(kind, token));
+                    }
+                },
+                _ => (),
+            }
+            // a whole form just ended, which completes any prefixes before it
+            while open.last() == Some(&"") {
+                open.pop();
+            }
+        }
+        Ok(open.len())
+    }
+
+    /// Reads `tokens` like `read`, but returns the top level forms as owned
+    /// trees instead of an `AST`.
+    pub fn read_tree(tokens: &[Token]) -> Result<Vec<Form>, ParseError> {
+        Ok(AST::read(tokens)?.tree())
+    }
+
+    /// Where each form starts, as `(line, col)` by form id.
+    pub fn source_map(&self) -> HashMap<usize, (usize, usize)> {
+        self.items
+            .iter()
+            .map(|(id, sexp)| (*id, (sexp.line, sexp.col)))
+            .collect()
+    }
+
+    /// The top level forms as owned trees.
+    pub fn tree(&self) -> Vec<Form> {
+        self.forms().map(|form| form.to_form()).collect()
+    }
+
+    /// Resolves every `Atom::Reference` in `atom` into the collection it
+    /// points at, producing a form the evaluator can work with directly.
+    /// Each id is resolved once, so a form referenced from several parents
+    /// is built once and shared.
+    pub fn form(&self, atom: &Atom) -> Atom {
+        lower(&self.items, atom)
+    }
+}
+
+fn visit_node<'a>(node: Node<'a>, f: &mut dyn FnMut(Node<'a>)) {
+    f(node);
+    if let Node::Form(form) = node {
+        for child in form.children() {
+            visit_node(child, f);
+        }
+    }
+}
+
+/// Adds the symbol, number or character that was being built up, if any,
+/// to `tokens`, rejecting it if it looks like a number or a character but
+/// isn't one.
+fn finish_word(tokens: &mut Vec<Token>, word: Option<Token>) -> Result<(), LexError> {
+    if let Some(word) = word {
+        if Atom::infer(&word.value, true).is_none() {
+            let (token, line, col) = (word.value, word.line, word.col);
+            return Err(if token.starts_with('\\') {
+                LexError::InvalidCharacter { token, line, col }
+            } else {
+                LexError::InvalidNumber { token, line, col }
+            });
+        }
+        tokens.push(word);
+    }
+    Ok(())
+}
+
+/// Gives a string literal the same allocation as any equal literal read
+/// before it, so a program that repeats a string holds it once.
+fn intern(strings: &mut HashSet<Rc<str>>, atom: Atom) -> Atom {
+    match atom {
+        Atom::String(s) => match strings.get(&s) {
+            Some(shared) => Atom::String(shared.clone()),
+            None => {
+                strings.insert(s.clone());
+                Atom::String(s)
+            }
+        },
+        atom => atom,
+    }
+}
+
+/// The top level forms `Reader::read` has read so far, and where each one
+/// starts.
+#[derive(Default)]
+struct Roots {
+    forms: Vec<Atom>,
+    positions: Vec<(usize, usize)>,
+}
+
+impl Roots {
+    fn push(&mut self, form: Atom, start: &Token) {
+        self.forms.push(form);
+        self.positions.push((start.line, start.col));
+    }
+}
+
+/// What a tagged literal's handler gets: the form after the tag, as data.
+/// It returns the value to read in its place, or a message saying why the
+/// form is no good.
+pub type TagHandler = Box<dyn Fn(Atom) -> Result<Atom, String>>;
+
+/// Turns tokens into an `AST`, reading tagged literals like `#inst "..."`
+/// with the handlers registered for their tags, and reader conditionals for
+/// its platform. `AST::read` is a reader with no tags for the `:warp`
+/// platform.
+pub struct Reader {
+    tags: HashMap<String, TagHandler>,
+    /// The feature, without its `:`, a reader conditional keeps the branch
+    /// of.
+    platform: String,
+}
+
+impl Default for Reader {
+    fn default() -> Reader {
+        Reader {
+            tags: HashMap::new(),
+            platform: "warp".to_string(),
+        }
+    }
+}
+
+impl Reader {
+    pub fn new() -> Reader {
+        Reader::default()
+    }
+
+    /// Reads reader conditionals for `platform`, given without its `:`,
+    /// instead of `warp`.
+    pub fn set_platform(&mut self, platform: &str) {
+        self.platform = platform.to_string();
+    }
+
+    /// Reads `#tag form` with `handler`, replacing any handler `tag` had.
+    /// The tag is given without its `#`.
+    pub fn register_tag<F>(&mut self, tag: &str, handler: F)
+    where
+        F: Fn(Atom) -> Result<Atom, String> + 'static,
+    {
+        self.tags.insert(tag.to_string(), Box::new(handler));
+    }
+
+    pub fn read(&self, tokens: &[Token]) -> Result<AST, ParseError> {
+        let mut items: HashMap<usize, SExp> = HashMap::new();
+        let mut roots = Roots::default();
+        let mut strings: HashSet<Rc<str>> = HashSet::new();
+        let mut sexps: Vec<SExp> = vec![];
+        let mut id: usize = 0;
+        let mut ids: Vec<usize> = vec![];
+        // the token that opened each form on `sexps`, for error positions
+        let mut openers: Vec<&Token> = vec![];
+
+        for token in tokens {
+            match token.value.as_str() {
+                // `'x` reads as `(quote x)`, `@x` as `(deref x)`, and `` `x ``,
+                // `~x` and `~@x` as `(syntax-quote x)`, `(unquote x)` and
+                // `(unquote-splicing x)`
+                "'" | "@" | "`" | "~" | "~@" => {
+                    match sexps.last_mut() {
+                        Some(sexp) => sexp.push(Atom::Reference(id)),
+                        None => roots.push(Atom::Reference(id), token),
+                    }
+                    ids.push(id);
+                    id += 1;
+                    let _type = match token.value.as_str() {
+                        "'" => "quote",
+                        "@" => "deref",
+                        "`" => "syntax-quote",
+                        "~" => "unquote",
+                        _ => "unquote-splicing",
+                    };
+                    sexps.push(SExp::prefix(_type, token));
+                    openers.push(token);
+                }
+                // like a quote, but nothing refers to it, and the form it
+                // wraps is thrown away once it's read
+                "#_" => {
+                    ids.push(id);
+                    id += 1;
+                    sexps.push(SExp::prefix("discard", token));
+                    openers.push(token);
+                }
+                "(" | "[" | "{" | "#{" | "#(" | "#?(" => {
+                    if token.value == "#(" && sexps.iter().any(|sexp| sexp._type == "fn") {
+                        return Err(ParseError::new(ParseErrorKind::NestedFnLiteral, token));
+                    }
+                    // like a tag, nothing refers to a reader conditional:
+                    // the branch it keeps takes its place once it's closed
+                    match sexps.last_mut() {
+                        _ if token.value == "#?(" => (),
+                        Some(sexp) => sexp.push(Atom::Reference(id)),
+                        None => roots.push(Atom::Reference(id), token),
+                    }
+                    ids.push(id);
+                    id += 1;
+                    sexps.push(SExp::new(token));
+                    openers.push(token);
+                }
+                ")" | "]" | "}" => {
+                    let sexp = match sexps.pop() {
+                        Some(sexp) if !sexp.is_prefix() => sexp,
+                        _ => return Err(ParseError::new(ParseErrorKind::UnexpectedClose, token)),
+                    };
+                    let opener = openers.pop().expect("an opener for every form");
+                    if sexp.closer() != token.value {
+                        let expected = sexp.closer();
+                        return Err(ParseError::new(
+                            ParseErrorKind::Mismatched { expected },
+                            token,
+                        ));
+                    }
+                    if sexp._type == "map" && sexp.children.len() % 2 != 0 {
+                        return Err(ParseError::new(ParseErrorKind::OddMap, opener));
+                    }
+                    if sexp._type == "conditional" {
+                        ids.pop();
+                        if let Some(branch) = self.conditional_branch(&sexp, &mut items, opener)? {
+                            match sexps.last_mut() {
+                                Some(parent) => parent.push(branch),
+                                None => roots.push(branch, opener),
+                            }
+                            self.close_prefixes(
+                                &mut sexps,
+                                &mut ids,
+                                &mut items,
+                                &mut openers,
+                                &mut roots,
+                            )?;
+                        }
+                        continue;
+                    }
+                    let keys: Vec<&Atom> = match sexp._type {
+                        "map" => sexp.children.iter().step_by(2).collect(),
+                        "set" => sexp.children.iter().collect(),
+                        _ => vec![],
+                    };
+                    let mut seen = HashSet::new();
+                    for key in keys {
+                        let key = lower(&items, key);
+                        if seen.contains(&key) {
+                            let kind = ParseErrorKind::DuplicateKey(key.to_string());
+                            return Err(ParseError::new(kind, opener));
+                        }
+                        seen.insert(key);
+                    }
+                    items.insert(ids.pop().expect("No more items left"), sexp);
+                    self.close_prefixes(
+                        &mut sexps,
+                        &mut ids,
+                        &mut items,
+                        &mut openers,
+                        &mut roots,
+                    )?;
+                }
+                // like a discard, nothing refers to it: once its form is
+                // read, the handler's value takes its place
+                _ if token.dispatch() == Some(Dispatch::Tag) => {
+                    if !self.tags.contains_key(&token.value[1..]) {
+                        return Err(ParseError::new(ParseErrorKind::UnknownTag, token));
+                    }
+                    ids.push(id);
+                    id += 1;
+                    sexps.push(SExp::prefix("tag", token));
+                    openers.push(token);
+                }
+                _ => {
+                    let atom = match token.dispatch() {
+                        Some(Dispatch::Regex) => {
+                            let source = &token.value[2..token.value.len() - 1];
+                            let regex = Regex::new(source).map_err(|err| {
+                                let kind = ParseErrorKind::InvalidRegex(err.to_string());
+                                ParseError::new(kind, token)
+                            })?;
+                            Atom::Regex(Rc::new(regex))
+                        }
+                        _ => intern(
+                            &mut strings,
+                            Atom::infer(&token.value, true)
+                                .expect("tokenize rejects tokens infer can't read"),
+                        ),
+                    };
+                    match sexps.last_mut() {
+                        Some(sexp) => sexp.push(atom),
+                        None => roots.push(atom, token),
+                    }
+                    self.close_prefixes(
+                        &mut sexps,
+                        &mut ids,
+                        &mut items,
+                        &mut openers,
+                        &mut roots,
+                    )?;
+                }
+            }
+        }
+
+        if let Some(opener) = openers.last() {
+            let kind = ParseErrorKind::Unclosed(sexps.len());
+            return Err(ParseError::new(kind, opener));
+        }
+
+        Ok(AST {
+            items,
+            roots: roots.forms,
+            positions: roots.positions,
+            tokens: tokens.len(),
+        })
+    }
+
+    /// The form a reader conditional keeps: the first one whose feature is
+    /// the platform or `:default`, or `None` if there's no such branch.
+    /// The forms of the other branches are discarded.
+    fn conditional_branch(
+        &self,
+        conditional: &SExp,
+        items: &mut HashMap<usize, SExp>,
+        opener: &Token,
+    ) -> Result<Option<Atom>, ParseError> {
+        let invalid = |message: &str| {
+            let kind = ParseErrorKind::InvalidConditional(message.to_string());
+            ParseError::new(kind, opener)
+        };
+        if conditional.children.len() % 2 == 1 {
+            return Err(invalid("expected feature and form pairs"));
+        }
+        let mut kept = None;
+        for pair in conditional.children.chunks(2) {
+            let matches = match &pair[0] {
+                Atom::Keyword(feature) => feature == &self.platform || feature == "default",
+                _ => return Err(invalid("features must be keywords")),
+            };
+            if matches && kept.is_none() {
+                kept = Some(pair[1].clone());
+            } else {
+                discard(items, &pair[1]);
+            }
+        }
+        Ok(kept)
+    }
+
+    /// Closes every prefix form on top of the stack that now has its form, so
+    /// `''x` closes both quotes as soon as `x` is read.
+    fn close_prefixes(
+        &self,
+        sexps: &mut Vec<SExp>,
+        ids: &mut Vec<usize>,
+        items: &mut HashMap<usize, SExp>,
+        openers: &mut Vec<&Token>,
+        roots: &mut Roots,
+    ) -> Result<(), ParseError> {
+        while sexps
+            .last()
+            .is_some_and(|sexp| sexp.is_prefix() && !sexp.children.is_empty())
+        {
+            let (id, sexp) = (ids.pop().unwrap(), sexps.pop().unwrap());
+            let opener = openers.pop().unwrap();
+            match sexp._type {
+                "discard" => {
+                    for child in &sexp.children {
+                        discard(items, child);
+                    }
+                }
+                "tag" => {
+                    let form = lower(items, &sexp.children[0]);
+                    discard(items, &sexp.children[0]);
+                    let value = (self.tags[&opener.value[1..]])(form).map_err(|message| {
+                        ParseError::new(ParseErrorKind::InvalidTagged(message), opener)
+                    })?;
+                    match sexps.last_mut() {
+                        Some(parent) => parent.push(value),
+                        None => roots.push(value, opener),
+                    }
+                }
+                _ => {
+                    items.insert(id, sexp);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Removes every form `atom` refers to, so nothing in a `#_` form is left
+/// behind in `items`.
+fn discard(items: &mut HashMap<usize, SExp>, atom: &Atom) {
+    if let Atom::Reference(id) = atom {
+        if let Some(sexp) = items.remove(id) {
+            for child in &sexp.children {
+                discard(items, child);
+            }
+        }
+    }
+}
+
+fn lower(items: &HashMap<usize, SExp>, atom: &Atom) -> Atom {
+    lower_shared(items, atom, &mut HashMap::new())
+}
+
+/// Like `lower`, resolving each id once: a form referenced from several
+/// parents is built the first time and `cache` hands out the same value
+/// after that, so its collections are shared rather than rebuilt.
+fn lower_shared(
+    items: &HashMap<usize, SExp>,
+    atom: &Atom,
+    cache: &mut HashMap<usize, Atom>,
+) -> Atom {
+    let id = match atom {
+        Atom::Reference(id) => id,
+        _ => return atom.clone(),
+    };
+    if let Some(lowered) = cache.get(id) {
+        return lowered.clone();
+    }
+    let sexp = &items[id];
+    let children: Vec<Atom> = sexp
+        .children
+        .iter()
+        .map(|child| lower_shared(items, child, cache))
+        .collect();
+    let lowered = match sexp._type {
+        "vec" => Atom::vector(children),
+        "map" => Atom::map(
+            children
+                .chunks(2)
+                .map(|pair| (pair[0].clone(), pair[1].clone())),
+        ),
+        "set" => Atom::set(children),
+        "fn" => expand_fn_literal(children),
+        "quote" | "deref" | "syntax-quote" | "unquote" | "unquote-splicing" => {
+            let mut quoted = vec![Atom::Symbol(sexp._type.to_string())];
+            quoted.extend(children);
+            Atom::list(quoted)
+        }
+        _ => Atom::list(children),
+    };
+    cache.insert(*id, lowered.clone());
+    lowered
+}
+
+/// Expands the body of `#(...)` into `(fn [%1 %2 & %&] (...))`, with as
+/// many positional parameters as the highest `%n` used. A bare `%` is `%1`.
+fn expand_fn_literal(body: Vec<Atom>) -> Atom {
+    let body = rename_percent(&Atom::list(body));
+    let mut max = 0;
+    let mut rest = false;
+    scan_fn_literal_args(&body, &mut max, &mut rest);
+
+    let mut params: Vec<Atom> = (1..=max).map(|n| Atom::Symbol(format!("%{}", n))).collect();
+    if rest {
+        params.push(Atom::Symbol("&".to_string()));
+        params.push(Atom::Symbol("%&".to_string()));
+    }
+    Atom::list(vec![
+        Atom::Symbol("fn".to_string()),
+        Atom::vector(params),
+        body,
+    ])
+}
+
+fn rename_percent(atom: &Atom) -> Atom {
+    match atom {
+        Atom::Symbol(name) if name == "%" => Atom::Symbol("%1".to_string()),
+        Atom::List(items) => Atom::list(items.iter().map(rename_percent)),
+        Atom::Vector(items) => Atom::vector(items.iter().map(rename_percent)),
+        Atom::Set(items) => Atom::set(items.iter().map(rename_percent)),
+        Atom::Map(map) => Atom::map(
+            map.iter()
+                .map(|(key, value)| (rename_percent(key), rename_percent(value))),
+        ),
+        atom => atom.clone(),
+    }
+}
+
+/// Finds the highest `%n` and whether `%&` is used anywhere in `atom`.
+fn scan_fn_literal_args(atom: &Atom, max: &mut usize, rest: &mut bool) {
+    match atom {
+        Atom::Symbol(name) if name == "%&" => *rest = true,
+        Atom::Symbol(name) => {
+            if let Some(Ok(n)) = name.strip_prefix('%').map(str::parse::<usize>) {
+                *max = (*max).max(n);
+            }
+        }
+        Atom::List(items) | Atom::Vector(items) => {
+            for item in items.iter() {
+                scan_fn_literal_args(item, max, rest);
+            }
+        }
+        Atom::Set(items) => {
+            for item in items.iter() {
+                scan_fn_literal_args(item, max, rest);
+            }
+        }
+        Atom::Map(map) => {
+            for (key, value) in map.iter() {
+                scan_fn_literal_args(key, max, rest);
+                scan_fn_literal_args(value, max, rest);
+            }
+        }
+        _ => (),
+    }
+}
@@ -0,0 +1,573 @@
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use crate::env::Scope;
+use crate::eval::EvalError;
+use crate::map::OrderedMap;
+use crate::regex::Regex;
+
+/// A value. Strings and collections are reference counted, so cloning an
+/// atom never copies its contents; changing a collection copies it only if
+/// it's shared (see `Rc::make_mut`). That copy is the whole collection, so
+/// `conj` onto a vector that's also bound somewhere else is O(n): these are
+/// copy-on-write, not persistent structures that share their unchanged parts.
+#[derive(Debug, Clone)]
+pub enum Atom {
+    Nil,
+    Symbol(String),
+    Keyword(String),
+    Int(i64),
+    Float(f64),
+    /// A fraction like `3/4`, always in lowest terms with a denominator
+    /// above 1, so equal ratios have equal fields.
+    Ratio(i64, i64),
+    String(Rc<str>),
+    /// A character literal like `\a` or `\newline`.
+    Char(char),
+    Boolean(bool),
+    Reference(usize),
+    List(Rc<Vec<Atom>>),
+    Vector(Rc<Vec<Atom>>),
+    Map(Rc<OrderedMap>),
+    Set(Rc<HashSet<Atom>>),
+    Lambda(Rc<Lambda>),
+    /// A function `defmacro` made, applied to unevaluated forms.
+    Macro(Rc<Lambda>),
+    Builtin(&'static str),
+    /// A function a builtin like `juxt` made, closing over its arguments.
+    Native(Rc<Native>),
+    /// A `#"..."` literal, compiled when it was read.
+    Regex(Rc<Regex>),
+    /// What `(atom x)` makes: a mutable reference, changed with `reset!` and
+    /// `swap!`.
+    Cell(Rc<RefCell<Atom>>),
+}
+
+/// A user defined function, closing over the local scope it was created in.
+#[derive(Debug)]
+pub struct Lambda {
+    pub name: Option<String>,
+    pub doc: Option<String>,
+    pub params: Vec<Atom>,
+    pub rest: Option<Atom>,
+    pub body: Vec<Atom>,
+    pub scope: Scope,
+}
+
+/// The functions builtins return. Each one remembers the functions it was
+/// made from.
+#[derive(Debug)]
+pub enum Native {
+    /// `(juxt f g)`: calls every function with the same arguments and
+    /// returns the results in a vector.
+    Juxt(Vec<Atom>),
+    /// `(partial f a b)`: calls `f` with `a` and `b` before its own
+    /// arguments.
+    Partial(Atom, Vec<Atom>),
+    /// `(comp f g)`: calls `g` with the arguments, then `f` with the result.
+    Comp(Vec<Atom>),
+    /// `(constantly x)`: ignores its arguments and returns `x`.
+    Constantly(Atom),
+    /// `(memoize f)`: calls `f`, remembering the result for each list of
+    /// arguments it's been called with.
+    Memoize(Atom, RefCell<HashMap<Vec<Atom>, Atom>>),
+    /// A function of the program embedding warp, see `ENV::define_native`.
+    Host(HostFn),
+}
+
+/// What a host function runs: it gets the evaluated arguments.
+pub type HostClosure = Box<dyn Fn(&[Atom]) -> Result<Atom, EvalError>>;
+
+/// A Rust closure callable from programs, with the name it was defined as.
+pub struct HostFn {
+    pub name: String,
+    pub f: HostClosure,
+}
+
+impl fmt::Debug for HostFn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HostFn").field("name", &self.name).finish()
+    }
+}
+
+impl Native {
+    /// The builtin that made this function, or the name a host function was
+    /// defined as.
+    pub fn name(&self) -> &str {
+        match self {
+            Native::Juxt(_) => "juxt",
+            Native::Partial(..) => "partial",
+            Native::Comp(_) => "comp",
+            Native::Constantly(_) => "constantly",
+            Native::Memoize(..) => "memoize",
+            Native::Host(host) => &host.name,
+        }
+    }
+}
+
+impl Atom {
+    /// Reads a token's text as an atom, with the first of `PARSERS` that
+    /// claims it. Only symbols, keywords and strings allocate. `None` for a
+    /// token that starts like a number but isn't one, like `1abc`, or like a
+    /// character but isn't one, like `\foo`: symbols can't start with either.
+    ///
+    /// With `literals` off, `true`, `false` and `nil` stay symbols, for
+    /// code that wants to treat them as names rather than values.
+    pub fn infer(src: &str, literals: bool) -> Option<Atom> {
+        PARSERS
+            .iter()
+            .filter(|(literal, _)| literals || !literal)
+            .find_map(|(_, parse)| parse(src))
+    }
+
+    pub fn string(s: impl Into<Rc<str>>) -> Atom {
+        Atom::String(s.into())
+    }
+
+    pub fn list(items: impl IntoIterator<Item = Atom>) -> Atom {
+        Atom::List(Rc::new(items.into_iter().collect()))
+    }
+
+    pub fn vector(items: impl IntoIterator<Item = Atom>) -> Atom {
+        Atom::Vector(Rc::new(items.into_iter().collect()))
+    }
+
+    pub fn map(entries: impl IntoIterator<Item = (Atom, Atom)>) -> Atom {
+        Atom::Map(Rc::new(entries.into_iter().collect()))
+    }
+
+    pub fn set(items: impl IntoIterator<Item = Atom>) -> Atom {
+        Atom::Set(Rc::new(items.into_iter().collect()))
+    }
+
+    /// `numerator/denominator` in lowest terms with a positive denominator,
+    /// or an int if it divides evenly. The arguments are wide so arithmetic
+    /// on ratios can't overflow before it's reduced. `None` for a zero
+    /// denominator, or when the reduced parts don't fit in an i64.
+    pub(crate) fn ratio(numerator: i128, denominator: i128) -> Option<Atom> {
+        if denominator == 0 {
+            return None;
+        }
+        let divisor = gcd(numerator.unsigned_abs(), denominator.unsigned_abs()) as i128;
+        let sign = denominator.signum();
+        let numerator = i64::try_from(sign * numerator / divisor).ok()?;
+        let denominator = i64::try_from(sign * denominator / divisor).ok()?;
+        if denominator == 1 {
+            Some(Atom::Int(numerator))
+        } else {
+            Some(Atom::Ratio(numerator, denominator))
+        }
+    }
+
+    /// Everything except `nil` and `false` is truthy.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Atom::Nil | Atom::Boolean(false))
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Atom::Nil => "nil",
+            Atom::Symbol(_) => "symbol",
+            Atom::Keyword(_) => "keyword",
+            Atom::Int(_) => "int",
+            Atom::Float(_) => "float",
+            Atom::Ratio(..) => "ratio",
+            Atom::String(_) => "string",
+            Atom::Char(_) => "char",
+            Atom::Boolean(_) => "boolean",
+            Atom::Reference(_) => "reference",
+            Atom::List(_) => "list",
+            Atom::Vector(_) => "vector",
+            Atom::Map(_) => "map",
+            Atom::Set(_) => "set",
+            Atom::Lambda(_) | Atom::Builtin(_) | Atom::Native(_) => "function",
+            Atom::Macro(_) => "macro",
+            Atom::Regex(_) => "regex",
+            Atom::Cell(_) => "atom",
+        }
+    }
+}
+
+impl PartialEq for Atom {
+    fn eq(&self, other: &Atom) -> bool {
+        match (self, other) {
+            (Atom::Nil, Atom::Nil) => true,
+            (Atom::Symbol(a), Atom::Symbol(b)) => a == b,
+            (Atom::Keyword(a), Atom::Keyword(b)) => a == b,
+            (Atom::Int(a), Atom::Int(b)) => a == b,
+            (Atom::Float(a), Atom::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (Atom::Ratio(a, b), Atom::Ratio(c, d)) => a == c && b == d,
+            (Atom::String(a), Atom::String(b)) => a == b,
+            (Atom::Char(a), Atom::Char(b)) => a == b,
+            (Atom::Boolean(a), Atom::Boolean(b)) => a == b,
+            (Atom::Reference(a), Atom::Reference(b)) => a == b,
+            // lists and vectors with the same elements are equal, like clojure
+            (Atom::List(a), Atom::List(b))
+            | (Atom::List(a), Atom::Vector(b))
+            | (Atom::Vector(a), Atom::List(b))
+            | (Atom::Vector(a), Atom::Vector(b)) => a == b,
+            (Atom::Map(a), Atom::Map(b)) => a == b,
+            (Atom::Set(a), Atom::Set(b)) => a == b,
+            (Atom::Lambda(a), Atom::Lambda(b)) | (Atom::Macro(a), Atom::Macro(b)) => {
+                Rc::ptr_eq(a, b)
+            }
+            (Atom::Builtin(a), Atom::Builtin(b)) => a == b,
+            (Atom::Native(a), Atom::Native(b)) => Rc::ptr_eq(a, b),
+            // like clojure, two patterns are only equal if they're the same one
+            (Atom::Regex(a), Atom::Regex(b)) => Rc::ptr_eq(a, b),
+            (Atom::Cell(a), Atom::Cell(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Atom {}
+
+impl Hash for Atom {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Atom::Nil => "nil".hash(state),
+            Atom::Boolean(f) => f.to_string().hash(state),
+            // -0.0 == 0.0, so they have to hash the same way too
+            Atom::Float(f) => (if *f == 0.0 { 0.0 } else { *f }).to_string().hash(state),
+            Atom::Int(f) => f.to_string().hash(state),
+            Atom::Ratio(n, d) => (n, d).hash(state),
+            Atom::Keyword(f) => f.to_string().hash(state),
+            Atom::Reference(f) => f.to_string().hash(state),
+            Atom::String(f) => f.to_string().hash(state),
+            Atom::Char(c) => c.hash(state),
+            Atom::Symbol(f) => f.to_string().hash(state),
+            Atom::List(items) | Atom::Vector(items) => items.hash(state),
+            Atom::Map(map) => unordered_hash(map.iter()).hash(state),
+            Atom::Set(set) => unordered_hash(set.iter()).hash(state),
+            Atom::Lambda(f) | Atom::Macro(f) => (Rc::as_ptr(f) as usize).hash(state),
+            Atom::Builtin(f) => f.hash(state),
+            Atom::Native(f) => (Rc::as_ptr(f) as usize).hash(state),
+            Atom::Regex(f) => (Rc::as_ptr(f) as usize).hash(state),
+            Atom::Cell(f) => (Rc::as_ptr(f) as usize).hash(state),
+        }
+    }
+}
+
+/// Maps and sets are equal whatever order their entries are in, so the entries
+/// are combined with an order independent sum.
+fn unordered_hash<T: Hash>(entries: impl Iterator<Item = T>) -> u64 {
+    let mut sum: u64 = 0;
+    for entry in entries {
+        let mut hasher = DefaultHasher::new();
+        entry.hash(&mut hasher);
+        sum = sum.wrapping_add(hasher.finish());
+    }
+    sum
+}
+
+impl fmt::Display for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_atom(f, self, false)
+    }
+}
+
+/// Displays an atom the way the reader would read it back: strings are
+/// quoted and escaped. Made with `Atom::readable`.
+pub struct Readable<'a>(&'a Atom);
+
+impl<'a> fmt::Display for Readable<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_atom(f, self.0, true)
+    }
+}
+
+impl Atom {
+    pub fn readable(&self) -> Readable<'_> {
+        Readable(self)
+    }
+}
+
+fn write_atom(f: &mut fmt::Formatter, atom: &Atom, readable: bool) -> fmt::Result {
+    match atom {
+        Atom::Nil => write!(f, "nil"),
+        Atom::Boolean(v) => write!(f, "{}", v),
+        Atom::Float(v) => write!(f, "{:?}", v),
+        Atom::Ratio(n, d) => write!(f, "{}/{}", n, d),
+        Atom::Int(v) => write!(f, "{}", v),
+        Atom::Keyword(v) => write!(f, ":{}", v),
+        Atom::Reference(v) => write!(f, "%{}", v),
+        Atom::String(v) if readable => write!(f, "\"{}\"", escape(v)),
+        Atom::String(v) => write!(f, "{}", v),
+        Atom::Char(v) if readable => match CHAR_NAMES.iter().find(|(_, c)| c == v) {
+            Some((name, _)) => write!(f, "\\{}", name),
+            None => write!(f, "\\{}", v),
+        },
+        Atom::Char(v) => write!(f, "{}", v),
+        Atom::Symbol(v) => write!(f, "{}", v),
+        Atom::List(v) => write_seq(f, "(", v.iter(), " ", ")", readable),
+        Atom::Vector(v) => write_seq(f, "[", v.iter(), " ", "]", readable),
+        Atom::Map(v) => {
+            write!(f, "{{")?;
+            for (i, (key, value)) in v.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write_atom(f, key, readable)?;
+                write!(f, " ")?;
+                write_atom(f, value, readable)?;
+            }
+            write!(f, "}}")
+        }
+        Atom::Set(v) => write_seq(f, "#{", v.iter(), " ", "}", readable),
+        Atom::Lambda(v) => match &v.name {
+            Some(name) => write!(f, "#<fn {}>", name),
+            None => write!(f, "#<fn>"),
+        },
+        Atom::Macro(v) => match &v.name {
+            Some(name) => write!(f, "#<macro {}>", name),
+            None => write!(f, "#<macro>"),
+        },
+        Atom::Builtin(v) => write!(f, "#<builtin {}>", v),
+        Atom::Native(v) => write!(f, "#<fn {}>", v.name()),
+        Atom::Regex(v) => write!(f, "{}", v),
+        Atom::Cell(v) => {
+            write!(f, "#<atom ")?;
+            write_atom(f, &v.borrow(), readable)?;
+            write!(f, ">")
+        }
+    }
+}
+
+fn write_seq<'a>(
+    f: &mut fmt::Formatter,
+    open: &str,
+    items: impl Iterator<Item = &'a Atom>,
+    separator: &str,
+    close: &str,
+    readable: bool,
+) -> fmt::Result {
+    write!(f, "{}", open)?;
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            write!(f, "{}", separator)?;
+        }
+        write_atom(f, item, readable)?;
+    }
+    write!(f, "{}", close)
+}
+
+type Parser = fn(&str) -> Option<Atom>;
+
+/// What a token can be read as, tried in order until one returns an atom.
+/// The order matters where two could claim the same token: `1/2` is a ratio
+/// because `try_ratio` comes before `try_symbol`, and `try_symbol` comes last
+/// so it only gets what nothing else wanted. A new kind of literal is a new
+/// entry here. The ones marked `true` read `true`, `false` and `nil`, and
+/// `infer` skips them with `literals` off.
+const PARSERS: &[(bool, Parser)] = &[
+    (false, try_string),
+    (true, try_bool),
+    (true, try_nil),
+    (false, try_int),
+    (false, try_ratio),
+    (false, try_float),
+    (false, try_char),
+    (false, try_keyword),
+    (false, try_symbol),
+];
+
+/// The characters with a name, like `\newline`, rather than just the
+/// character after the `\`.
+const CHAR_NAMES: &[(&str, char)] = &[
+    ("newline", '\n'),
+    ("space", ' '),
+    ("tab", '\t'),
+    ("return", '\r'),
+    ("backspace", '\u{8}'),
+    ("formfeed", '\u{c}'),
+];
+
+/// String literals come through with their quotes and escapes intact.
+fn try_string(src: &str) -> Option<Atom> {
+    let body = src.strip_prefix('"')?.strip_suffix('"')?;
+    Some(Atom::string(unescape(body)))
+}
+
+fn try_bool(src: &str) -> Option<Atom> {
+    match src {
+        "true" => Some(Atom::Boolean(true)),
+        "false" => Some(Atom::Boolean(false)),
+        _ => None,
+    }
+}
+
+fn try_nil(src: &str) -> Option<Atom> {
+    match src {
+        "nil" => Some(Atom::Nil),
+        _ => None,
+    }
+}
+
+/// Decimal, or with a radix: 0x1F, 0o17, 0b1010 and clojure's 2r1010.
+fn try_int(src: &str) -> Option<Atom> {
+    src.parse::<i64>()
+        .ok()
+        .or_else(|| parse_radix(src))
+        .map(Atom::Int)
+}
+
+/// 3/4. A zero denominator or a second `/` isn't a ratio, and then isn't a
+/// number at all.
+fn try_ratio(src: &str) -> Option<Atom> {
+    if looks_numeric(src) && src.contains('/') {
+        parse_ratio(src)
+    } else {
+        None
+    }
+}
+
+/// As long as it looks like a number: rust would happily parse symbols like
+/// `inf` and `NaN` too.
+fn try_float(src: &str) -> Option<Atom> {
+    if looks_numeric(src) {
+        src.parse::<f64>().ok().map(Atom::Float)
+    } else {
+        None
+    }
+}
+
+/// `\a`, a named character like `\newline`, or a code point like `\u00e9`.
+fn try_char(src: &str) -> Option<Atom> {
+    let name = src.strip_prefix('\\')?;
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(Atom::Char(c));
+    }
+    if let Some((_, c)) = CHAR_NAMES.iter().find(|(known, _)| *known == name) {
+        return Some(Atom::Char(*c));
+    }
+    let hex = name.strip_prefix('u').filter(|hex| hex.len() == 4)?;
+    let code = u32::from_str_radix(hex, 16).ok()?;
+    char::from_u32(code).map(Atom::Char)
+}
+
+fn try_keyword(src: &str) -> Option<Atom> {
+    match src.strip_prefix(':') {
+        Some(name) if !name.is_empty() => Some(Atom::Keyword(name.to_string())),
+        _ => None,
+    }
+}
+
+/// Anything else, unless it starts like a number, a string or a character:
+/// that's one the parsers before this one couldn't read.
+fn try_symbol(src: &str) -> Option<Atom> {
+    if looks_numeric(src) || src.starts_with(['"', '\\']) {
+        None
+    } else {
+        Some(Atom::Symbol(src.to_string()))
+    }
+}
+
+/// Whether `src` starts like a number: a digit, optionally after a sign
+/// and/or a decimal point.
+fn looks_numeric(src: &str) -> bool {
+    let src = src.strip_prefix(|c| c == '-' || c == '+').unwrap_or(src);
+    let src = src.strip_prefix('.').unwrap_or(src);
+    src.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Parses an integer literal with a radix prefix. Anything that doesn't
+/// fit, like a digit that's out of range for the radix, isn't a radix
+/// literal, and then isn't a number at all.
+fn parse_radix(src: &str) -> Option<i64> {
+    let (negative, unsigned) = match src.as_bytes().first()? {
+        b'-' => (true, &src[1..]),
+        b'+' => (false, &src[1..]),
+        _ => (false, src),
+    };
+    let (radix, digits) = match unsigned.as_bytes() {
+        [b'0', b'x' | b'X', ..] => (16, &unsigned[2..]),
+        [b'0', b'o' | b'O', ..] => (8, &unsigned[2..]),
+        [b'0', b'b' | b'B', ..] => (2, &unsigned[2..]),
+        _ => {
+            let (radix, digits) = unsigned.split_once(['r', 'R'])?;
+            if radix.is_empty() || !radix.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            match radix.parse::<u32>() {
+                Ok(radix) if (2..=36).contains(&radix) => (radix, digits),
+                _ => return None,
+            }
+        }
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        return None;
+    }
+    // parse the magnitude unsigned so i64::MIN still fits
+    let magnitude = u64::from_str_radix(digits, radix).ok()?;
+    if negative {
+        0i64.checked_sub_unsigned(magnitude)
+    } else {
+        i64::try_from(magnitude).ok()
+    }
+}
+
+/// Parses `n/d` into a ratio in lowest terms, or an int if it divides
+/// evenly, like `4/2`. The denominator can't have a sign.
+fn parse_ratio(src: &str) -> Option<Atom> {
+    let (numerator, denominator) = src.split_once('/')?;
+    if denominator.is_empty() || !denominator.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let numerator = numerator.parse::<i64>().ok()?;
+    let denominator = denominator.parse::<i64>().ok()?;
+    Atom::ratio(numerator.into(), denominator.into())
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// The inverse of `unescape`.
+fn escape(src: &str) -> String {
+    let mut result = String::with_capacity(src.len());
+    for c in src.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            '\0' => result.push_str("\\0"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Decodes the escapes in the body of a string literal. The tokenizer has
+/// already rejected anything not in `ast::ESCAPES`.
+fn unescape(src: &str) -> String {
+    let mut result = String::with_capacity(src.len());
+    let mut chars = src.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('0') => result.push('\0'),
+            Some(other) => result.push(other),
+            None => (),
+        }
+    }
+    result
+}
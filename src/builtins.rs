@@ -0,0 +1,58 @@
+//! The table of builtins, each a plain function looked up by name. Every
+//! `ENV` has its own table, so a program embedding warp can add host
+//! functions with `ENV::register_builtin`, or replace warp's own.
+
+use std::collections::HashMap;
+
+use crate::atom::Atom;
+use crate::env::ENV;
+use crate::eval::{EvalError, ARITHMETIC, CORE};
+
+/// A builtin in the table. It gets the evaluated arguments, and owns them so
+/// that e.g. `conj` can add to a collection nothing else holds in place.
+pub type BuiltinFn = fn(Vec<Atom>, &mut ENV) -> Result<Atom, EvalError>;
+
+#[derive(Clone)]
+pub struct Builtins {
+    functions: HashMap<&'static str, BuiltinFn>,
+}
+
+impl Builtins {
+    /// A table with nothing in it.
+    pub fn new() -> Builtins {
+        Builtins {
+            functions: HashMap::new(),
+        }
+    }
+
+    /// The table every `ENV` starts with.
+    pub fn standard() -> Builtins {
+        let mut builtins = Builtins::new();
+        for (name, f) in ARITHMETIC.iter().chain(CORE) {
+            builtins.register(name, *f);
+        }
+        builtins
+    }
+
+    /// Adds `f` as `name`, replacing whatever `name` was.
+    pub fn register(&mut self, name: &'static str, f: BuiltinFn) {
+        self.functions.insert(name, f);
+    }
+
+    /// The builtin called `name`, with the name as it was registered.
+    pub fn get(&self, name: &str) -> Option<(&'static str, BuiltinFn)> {
+        self.functions
+            .get_key_value(name)
+            .map(|(name, f)| (*name, *f))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.functions.keys().copied()
+    }
+}
+
+impl Default for Builtins {
+    fn default() -> Builtins {
+        Builtins::standard()
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::ast::AST;
+use crate::atom::Atom;
+use crate::eval::{docstring, BUILTINS, CONSTANTS, SPECIAL_FORMS};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in a program without running it.
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// The id of the innermost form the problem was found in, if any.
+    pub form: Option<usize>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+impl AST {
+    /// Flags every symbol that isn't a local, a builtin, a constant, a special
+    /// form or defined somewhere in the program.
+    pub fn check(&self) -> Vec<Diagnostic> {
+        let mut known: HashSet<String> = BUILTINS
+            .iter()
+            .map(|(name, _)| name)
+            .chain(CONSTANTS.iter().map(|(name, _)| name))
+            .chain(SPECIAL_FORMS)
+            .map(|name| name.to_string())
+            .collect();
+        // any def in the program counts, so forward references aren't flagged
+        for id in self.items.keys() {
+            known.extend(self.defined_names(*id));
+        }
+
+        let mut checker = Checker {
+            ast: self,
+            known,
+            diagnostics: vec![],
+        };
+        for root in &self.roots {
+            checker.walk(root, &[], None);
+        }
+        checker.diagnostics
+    }
+
+    /// The names a `(def name ...)` style form binds, or `(declare ...)`
+    /// promises.
+    fn defined_names(&self, id: usize) -> Vec<String> {
+        let sexp = &self.items[&id];
+        match sexp.children.as_slice() {
+            [Atom::Symbol(head), Atom::Symbol(name), ..]
+                if sexp._type == "exec"
+                    && ["def", "defonce", "defn", "defmacro"].contains(&head.as_str()) =>
+            {
+                vec![name.clone()]
+            }
+            [Atom::Symbol(head), names @ ..] if sexp._type == "exec" && head == "declare" => names
+                .iter()
+                .filter_map(|name| match name {
+                    Atom::Symbol(name) => Some(name.clone()),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+struct Checker<'a> {
+    ast: &'a AST,
+    known: HashSet<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+    fn walk(&mut self, atom: &Atom, locals: &[String], form: Option<usize>) {
+        match atom {
+            Atom::Symbol(name) if !locals.contains(name) && !self.known.contains(name) => {
+                self.diagnostics.push(Diagnostic {
+                    severity: Severity::Error,
+                    message: format!("unable to resolve symbol: {}", name),
+                    form,
+                });
+            }
+            Atom::Reference(id) => self.walk_form(*id, locals),
+            _ => (),
+        }
+    }
+
+    /// Walks only the `~` and `~@` forms inside a syntax-quoted form, the
+    /// only parts of it that are evaluated.
+    fn walk_unquoted(&mut self, id: usize, locals: &[String]) {
+        let ast = self.ast;
+        let sexp = &ast.items[&id];
+        if matches!(sexp._type, "unquote" | "unquote-splicing") {
+            return self.walk_all(&sexp.children, locals, id);
+        }
+        for child in &sexp.children {
+            if let Atom::Reference(child) = child {
+                self.walk_unquoted(*child, locals);
+            }
+        }
+    }
+
+    fn walk_all(&mut self, atoms: &[Atom], locals: &[String], form: usize) {
+        for atom in atoms {
+            self.walk(atom, locals, Some(form));
+        }
+    }
+
+    fn walk_form(&mut self, id: usize, locals: &[String]) {
+        let ast = self.ast;
+        let sexp = &ast.items[&id];
+        if sexp._type == "quote" {
+            return;
+        }
+        if sexp._type == "syntax-quote" {
+            return self.walk_unquoted(id, locals);
+        }
+        if sexp._type == "fn" {
+            // the `%` parameters of `#(...)` are implied by their use
+            let mut locals = locals.to_vec();
+            self.fn_literal_args(id, &mut locals);
+            return self.walk_all(&sexp.children, &locals, id);
+        }
+        let head = match (sexp._type, sexp.children.first()) {
+            ("exec", Some(Atom::Symbol(head))) if !locals.contains(head) => head.as_str(),
+            _ => return self.walk_all(&sexp.children, locals, id),
+        };
+        let args = &sexp.children[1..];
+        match head {
+            "quote" => (),
+            "def" | "defonce" => self.walk_all(args.get(1..).unwrap_or(&[]), locals, id),
+            "defn" | "defmacro" => {
+                let (_, rest) = docstring(args.get(1..).unwrap_or(&[]));
+                self.walk_fn(rest, locals, id)
+            }
+            "fn" => match args {
+                [Atom::Symbol(name), rest @ ..] => {
+                    let mut locals = locals.to_vec();
+                    locals.push(name.clone());
+                    self.walk_fn(rest, &locals, id)
+                }
+                _ => self.walk_fn(args, locals, id),
+            },
+            "let" | "loop" | "doseq" | "for" | "if-let" | "when-let" => match args.split_first() {
+                Some((Atom::Reference(bindings), body)) => {
+                    let mut locals = locals.to_vec();
+                    let pairs = &ast.items[bindings].children;
+                    for pair in pairs.chunks(2) {
+                        if let [pattern, value] = pair {
+                            self.walk(value, &locals, Some(*bindings));
+                            self.bound_names(pattern, &mut locals);
+                        }
+                    }
+                    self.walk_all(body, &locals, id)
+                }
+                _ => self.walk_all(args, locals, id),
+            },
+            // the test constants aren't evaluated, only the value and results
+            "case" => {
+                if let Some((value, clauses)) = args.split_first() {
+                    self.walk(value, locals, Some(id));
+                    for (i, clause) in clauses.iter().enumerate() {
+                        let is_test = i % 2 == 0 && i + 1 < clauses.len();
+                        if !is_test {
+                            self.walk(clause, locals, Some(id));
+                        }
+                    }
+                }
+            }
+            _ => self.walk_all(&sexp.children, locals, id),
+        }
+    }
+
+    /// Walks `[params] body...` with the parameters in scope.
+    fn walk_fn(&mut self, args: &[Atom], locals: &[String], form: usize) {
+        match args.split_first() {
+            Some((params @ Atom::Reference(_), body)) => {
+                let mut locals = locals.to_vec();
+                self.bound_names(params, &mut locals);
+                self.walk_all(body, &locals, form)
+            }
+            _ => self.walk_all(args, locals, form),
+        }
+    }
+
+    /// Collects the `%`, `%n` and `%&` symbols used inside a form.
+    fn fn_literal_args(&self, id: usize, locals: &mut Vec<String>) {
+        for child in &self.ast.items[&id].children {
+            match child {
+                Atom::Symbol(name) if name.starts_with('%') => locals.push(name.clone()),
+                Atom::Reference(id) => self.fn_literal_args(*id, locals),
+                _ => (),
+            }
+        }
+    }
+
+    /// Collects every symbol a binding form introduces.
+    fn bound_names(&self, pattern: &Atom, locals: &mut Vec<String>) {
+        match pattern {
+            Atom::Symbol(name) if name != "&" => locals.push(name.clone()),
+            Atom::Reference(id) => {
+                for child in &self.ast.items[id].children {
+                    self.bound_names(child, locals);
+                }
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use crate::atom::{Atom, HostFn, Native};
+use crate::builtins::{BuiltinFn, Builtins};
+use crate::eval::EvalError;
+
+/// The global environment: top level definitions and the writer that
+/// `print`/`println` send their output to.
+#[allow(clippy::upper_case_acronyms)]
+pub struct ENV {
+    pub vars: HashMap<Atom, Atom>,
+    /// Names `declare` promised a definition for. A name stays here once
+    /// it's defined, but `vars` is looked at first.
+    pub declared: HashSet<String>,
+    /// Whether `assert` checks anything. On by default.
+    pub assertions: bool,
+    /// Whether every function call and what it returns is written to the
+    /// output writer. Off by default.
+    pub trace: bool,
+    /// How many traced calls are running, for indenting the trace.
+    pub(crate) trace_depth: usize,
+    out: Box<dyn Write>,
+    /// The state of the generator behind `rand` and `rand-int`.
+    rng: u64,
+    /// The names `define` bound while `record_definitions` is running.
+    defined: Option<Vec<String>>,
+    builtins: Builtins,
+    /// How many names `gensym` has made.
+    gensyms: usize,
+}
+
+impl ENV {
+    pub fn new() -> ENV {
+        ENV::with_output(Box::new(io::stdout()))
+    }
+
+    pub fn with_output(out: Box<dyn Write>) -> ENV {
+        ENV {
+            vars: HashMap::new(),
+            declared: HashSet::new(),
+            assertions: true,
+            trace: false,
+            trace_depth: 0,
+            out,
+            // std has no random numbers, but its hash maps get random keys
+            rng: RandomState::new().build_hasher().finish(),
+            defined: None,
+            builtins: Builtins::standard(),
+            gensyms: 0,
+        }
+    }
+
+    pub fn define(&mut self, name: &str, value: Atom) {
+        if let Some(defined) = &mut self.defined {
+            if !defined.iter().any(|defined| defined == name) {
+                defined.push(name.to_string());
+            }
+        }
+        self.vars.insert(Atom::Symbol(name.to_string()), value);
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<&Atom> {
+        self.vars.get(&Atom::Symbol(name.to_string()))
+    }
+
+    /// Makes `f` callable from programs as `name`. It's found before any
+    /// builtin warp has of that name, but after anything `def`ined.
+    pub fn register_builtin(&mut self, name: &'static str, f: BuiltinFn) {
+        self.builtins.register(name, f);
+    }
+
+    /// A new symbol name starting with `prefix`, different every time, for
+    /// macros to bind without clashing with the caller's names.
+    pub fn gensym(&mut self, prefix: &str) -> String {
+        self.gensyms += 1;
+        format!("{}__{}__auto__", prefix, self.gensyms)
+    }
+
+    /// Defines `name` as a function that calls `f` with its evaluated
+    /// arguments. Unlike `register_builtin`, `f` can be a closure holding
+    /// state of the program embedding warp. It's a definition like any
+    /// other, so `def` can replace it.
+    pub fn define_native<F>(&mut self, name: &str, f: F)
+    where
+        F: Fn(&[Atom]) -> Result<Atom, EvalError> + 'static,
+    {
+        let host = HostFn {
+            name: name.to_string(),
+            f: Box::new(f),
+        };
+        self.define(name, Atom::Native(Rc::new(Native::Host(host))));
+    }
+
+    /// The registered builtin called `name`, if there is one.
+    pub fn builtin(&self, name: &str) -> Option<(&'static str, BuiltinFn)> {
+        self.builtins.get(name)
+    }
+
+    pub fn out(&mut self) -> &mut dyn Write {
+        &mut *self.out
+    }
+
+    /// Runs `f` with the output going to a buffer instead, returning what
+    /// `f` returned and everything it wrote. The old writer is back in place
+    /// afterwards, whatever `f` returned.
+    pub fn capture_output<T>(&mut self, f: impl FnOnce(&mut ENV) -> T) -> (T, Vec<u8>) {
+        let buffer = Rc::new(RefCell::new(vec![]));
+        let out = std::mem::replace(&mut self.out, Box::new(Capture(buffer.clone())));
+        let result = f(self);
+        self.out = out;
+        (result, buffer.take())
+    }
+
+    /// Runs `f`, returning what it returned and every name it defined or
+    /// redefined, in the order they were first defined.
+    pub fn record_definitions<T>(&mut self, f: impl FnOnce(&mut ENV) -> T) -> (T, Vec<String>) {
+        let outer = self.defined.replace(vec![]);
+        let result = f(self);
+        let defined = std::mem::replace(&mut self.defined, outer).unwrap_or_default();
+        // a recording around this one saw the same definitions
+        if let Some(outer) = &mut self.defined {
+            for name in &defined {
+                if !outer.contains(name) {
+                    outer.push(name.clone());
+                }
+            }
+        }
+        (result, defined)
+    }
+
+    /// Restarts the random number generator from `seed`, so the numbers
+    /// `rand` and `rand-int` return next are the same every time.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = seed;
+    }
+
+    /// The next number from the generator, a splitmix64: small and good
+    /// enough for games and tests, but not for anything secret.
+    pub fn next_random(&mut self) -> u64 {
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Takes a copy of the top level definitions to `restore` later.
+    pub fn snapshot(&self) -> EnvSnapshot {
+        EnvSnapshot {
+            vars: self.vars.clone(),
+            declared: self.declared.clone(),
+        }
+    }
+
+    /// Puts the top level definitions back as they were at `snapshot`: defs
+    /// made since are gone and redefined names get their old values back.
+    /// Values are shared with the snapshot rather than copied, so an atom
+    /// changed with `swap!` or `reset!` stays changed.
+    pub fn restore(&mut self, snapshot: EnvSnapshot) {
+        self.vars = snapshot.vars;
+        self.declared = snapshot.declared;
+    }
+}
+
+/// A writer into a buffer that `capture_output` keeps hold of.
+struct Capture(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The top level definitions of an `ENV` at some point, see `ENV::snapshot`.
+#[derive(Debug, Clone)]
+pub struct EnvSnapshot {
+    vars: HashMap<Atom, Atom>,
+    declared: HashSet<String>,
+}
+
+impl Default for ENV {
+    fn default() -> ENV {
+        ENV::new()
+    }
+}
+
+/// Local bindings introduced by `let`, `fn` and friends. Extending a scope
+/// never modifies it, so closures can hold on to the scope they were made in.
+#[derive(Debug, Clone, Default)]
+pub struct Scope(Option<Rc<Binding>>);
+
+#[derive(Debug)]
+struct Binding {
+    name: String,
+    value: Atom,
+    parent: Scope,
+}
+
+impl Scope {
+    pub fn bind(&self, name: &str, value: Atom) -> Scope {
+        Scope(Some(Rc::new(Binding {
+            name: name.to_string(),
+            value,
+            parent: self.clone(),
+        })))
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<&Atom> {
+        let mut scope = self;
+        while let Some(binding) = &scope.0 {
+            if binding.name == name {
+                return Some(&binding.value);
+            }
+            scope = &binding.parent;
+        }
+        None
+    }
+}
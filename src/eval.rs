@@ -0,0 +1,2555 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::ast::AST;
+use crate::atom::{Atom, Lambda, Native};
+use crate::builtins::BuiltinFn;
+use crate::env::{Scope, ENV};
+use crate::map::OrderedMap;
+use crate::regex::{Captures, Regex};
+use crate::syntax_quote::syntax_quote;
+
+#[derive(Debug, PartialEq)]
+pub enum EvalError {
+    /// A symbol with no local, global or builtin binding.
+    Unbound(String),
+    /// A symbol that was `declare`d but hasn't been defined yet.
+    Undefined(String),
+    /// Something in head position that can't be applied.
+    NotCallable(String),
+    Arity {
+        name: String,
+        got: usize,
+    },
+    Type {
+        expected: &'static str,
+        got: String,
+    },
+    /// A special form used with the wrong shape, e.g. `(def)`.
+    Malformed(String),
+    DivideByZero,
+    Overflow,
+    Io(String),
+    /// Not really an error: `recur` unwinds to the innermost `loop` or `fn`
+    /// with the values to run it again with. `eval` checks that a `recur`
+    /// is always in tail position, so it can't unwind past anything else.
+    Recur(Vec<Atom>),
+    /// Not really an error either: `(exit code)` unwinds all the way out so
+    /// whoever is running the program decides how to stop.
+    Exit(i32),
+    /// `pop` of an empty list or vector.
+    EmptyPop(&'static str),
+    /// An index past the end of something `count` long, or below 0.
+    IndexOutOfBounds {
+        index: i64,
+        count: usize,
+    },
+    /// A `case` with no clause for the value and no default.
+    NoMatchingClause(String),
+    /// A failed `assert`, with its message and the form that was falsey.
+    Assertion {
+        message: Option<String>,
+        form: String,
+    },
+    /// `error`, raised by the top level form that starts at `line` and `col`.
+    /// Only `eval_located` adds these.
+    At {
+        line: usize,
+        col: usize,
+        error: Box<EvalError>,
+    },
+}
+
+impl EvalError {
+    /// Where the form that failed starts, as `(line, col)`, if it's known.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match self {
+            EvalError::At { line, col, .. } => Some((*line, *col)),
+            _ => None,
+        }
+    }
+
+    /// What went wrong, without the position.
+    pub fn message(&self) -> String {
+        match self {
+            EvalError::At { error, .. } => error.message(),
+            error => error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalError::Unbound(name) => write!(f, "unable to resolve symbol: {}", name),
+            EvalError::Undefined(name) => write!(f, "{} is declared but not defined yet", name),
+            EvalError::NotCallable(value) => write!(f, "{} is not a function", value),
+            EvalError::Arity { name, got } => {
+                write!(f, "wrong number of args ({}) passed to {}", got, name)
+            }
+            EvalError::Type { expected, got } => write!(f, "expected {}, got {}", expected, got),
+            EvalError::Malformed(message) => write!(f, "{}", message),
+            EvalError::DivideByZero => write!(f, "divide by zero"),
+            EvalError::Overflow => write!(f, "integer overflow"),
+            EvalError::Io(message) => write!(f, "{}", message),
+            EvalError::Recur(_) => write!(f, "recur outside of a loop or fn"),
+            EvalError::Exit(code) => write!(f, "exit with status {}", code),
+            EvalError::EmptyPop(kind) => write!(f, "can't pop empty {}", kind),
+            EvalError::IndexOutOfBounds { index, count } => {
+                write!(f, "index {} out of bounds for count {}", index, count)
+            }
+            EvalError::NoMatchingClause(value) => write!(f, "no matching clause: {}", value),
+            EvalError::Assertion { message, form } => match message {
+                Some(message) => write!(f, "assert failed: {}\n{}", message, form),
+                None => write!(f, "assert failed: {}", form),
+            },
+            EvalError::At { line, col, error } => write!(f, "{}:{}: {}", line, col, error),
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, EvalError>;
+
+/// Evaluates every top level form of `ast` in order, returning the value of
+/// the last one.
+///
+/// Each root is resolved once, with shared sub-forms resolved once too (see
+/// `AST::form`). Their results aren't memoized: the same form can mean
+/// something different each time it runs, as in `(doseq [x xs] (println x))`
+/// or a `fn` body called with different arguments, and any call can `def`
+/// or print.
+pub fn eval(ast: &AST, env: &mut ENV) -> Result<Atom> {
+    let mut result = Atom::Nil;
+    for root in &ast.roots {
+        let form = ast.form(root);
+        check_recur(&form, false, env)?;
+        result = eval_form(&form, env, &Scope::default())?;
+    }
+    Ok(result)
+}
+
+/// Like `eval`, but an error says which top level form it came from: it's
+/// wrapped in an `EvalError::At` with the position in `ast.positions`.
+/// `exit` is passed through as it is, since it isn't really an error.
+pub fn eval_located(ast: &AST, env: &mut ENV) -> Result<Atom> {
+    let mut result = Atom::Nil;
+    for (i, root) in ast.roots.iter().enumerate() {
+        let form = ast.form(root);
+        result = check_recur(&form, false, env)
+            .and_then(|()| eval_form(&form, env, &Scope::default()))
+            .map_err(|error| match (error, ast.positions.get(i)) {
+                (EvalError::Exit(code), _) => EvalError::Exit(code),
+                (error, Some(&(line, col))) => EvalError::At {
+                    line,
+                    col,
+                    error: Box::new(error),
+                },
+                (error, None) => error,
+            })?;
+    }
+    Ok(result)
+}
+
+/// Like `eval`, but also returns the top level names the program defined or
+/// redefined, in the order they were first defined, so a REPL can say what
+/// a line defined.
+pub fn eval_defining(ast: &AST, env: &mut ENV) -> Result<(Atom, Vec<String>)> {
+    let (result, defined) = env.record_definitions(|env| eval(ast, env));
+    result.map(|value| (value, defined))
+}
+
+pub fn eval_form(form: &Atom, env: &mut ENV, scope: &Scope) -> Result<Atom> {
+    match form {
+        Atom::Symbol(name) => resolve(name, env, scope),
+        Atom::List(items) if items.is_empty() => Ok(form.clone()),
+        Atom::List(items) => {
+            if let Atom::Symbol(head) = &items[0] {
+                if scope.lookup(head).is_none() {
+                    if let Some(result) = special_form(head, &items[1..], env, scope) {
+                        return result;
+                    }
+                    if let Some(expansion) = macroexpand_1(form, env)? {
+                        return eval_form(&expansion, env, scope);
+                    }
+                }
+            }
+            let f = eval_form(&items[0], env, scope)?;
+            if env.trace {
+                // traced as written, before the arguments, so the calls that
+                // work them out nest under this one
+                let call_line = form.readable().to_string();
+                return traced(&call_line, env, |env| {
+                    let args = eval_all(&items[1..], env, scope)?;
+                    call(&f, args, env)
+                });
+            }
+            let args = eval_all(&items[1..], env, scope)?;
+            apply(&f, args, env)
+        }
+        Atom::Vector(items) => Ok(Atom::vector(eval_all(items, env, scope)?)),
+        Atom::Map(map) => {
+            let mut result = OrderedMap::new();
+            for (key, value) in map.iter() {
+                let key = eval_form(key, env, scope)?;
+                let value = eval_form(value, env, scope)?;
+                result.insert(key, value);
+            }
+            Ok(Atom::Map(Rc::new(result)))
+        }
+        Atom::Set(set) => {
+            let mut result = HashSet::new();
+            for item in set.iter() {
+                result.insert(eval_form(item, env, scope)?);
+            }
+            Ok(Atom::set(result))
+        }
+        _ => Ok(form.clone()),
+    }
+}
+
+fn eval_all(forms: &[Atom], env: &mut ENV, scope: &Scope) -> Result<Vec<Atom>> {
+    forms
+        .iter()
+        .map(|form| eval_form(form, env, scope))
+        .collect()
+}
+
+/// Evaluates `body` in order, returning the value of the last form.
+fn eval_body(body: &[Atom], env: &mut ENV, scope: &Scope) -> Result<Atom> {
+    let mut result = Atom::Nil;
+    for form in body {
+        result = eval_form(form, env, scope)?;
+    }
+    Ok(result)
+}
+
+fn resolve(name: &str, env: &ENV, scope: &Scope) -> Result<Atom> {
+    if let Some(value) = scope.lookup(name) {
+        return Ok(value.clone());
+    }
+    resolve_global(name, env).ok_or_else(|| {
+        if env.declared.contains(name) {
+            EvalError::Undefined(name.to_string())
+        } else {
+            EvalError::Unbound(name.to_string())
+        }
+    })
+}
+
+/// What `name` means outside of any local binding: a `def`, a builtin or a
+/// constant like `pi`.
+fn resolve_global(name: &str, env: &ENV) -> Option<Atom> {
+    if let Some(value) = env.lookup(name) {
+        return Some(value.clone());
+    }
+    if let Some((_, value)) = CONSTANTS.iter().find(|(constant, _)| *constant == name) {
+        return Some(Atom::Float(*value));
+    }
+    env.builtin(name).map(|(name, _)| Atom::Builtin(name))
+}
+
+/// Expands `form` once if it's a call to a macro or a syntax-quote,
+/// returning `None` when it isn't.
+pub fn macroexpand_1(form: &Atom, env: &mut ENV) -> Result<Option<Atom>> {
+    let items = match form {
+        Atom::List(items) => items,
+        _ => return Ok(None),
+    };
+    match items.as_slice() {
+        [Atom::Symbol(head), quoted] if head == "syntax-quote" => {
+            return syntax_quote(quoted, env).map(Some)
+        }
+        [Atom::Symbol(head), ..] if head == "syntax-quote" => {
+            return Err(malformed("syntax-quote", "expected exactly one form"))
+        }
+        _ => (),
+    }
+    let lambda = match items.first() {
+        Some(Atom::Symbol(head)) => match env.lookup(head) {
+            Some(Atom::Macro(lambda)) => lambda.clone(),
+            _ => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    call_lambda(&lambda, items[1..].to_vec(), env).map(Some)
+}
+
+/// Expands `form` until it's no longer a macro call.
+pub fn macroexpand(form: &Atom, env: &mut ENV) -> Result<Atom> {
+    let mut form = form.clone();
+    while let Some(expansion) = macroexpand_1(&form, env)? {
+        form = expansion;
+    }
+    Ok(form)
+}
+
+pub fn apply(f: &Atom, args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    if env.trace {
+        return apply_traced(f, args, env);
+    }
+    call(f, args, env)
+}
+
+/// `apply` for a call that isn't in the source, like `f` in `(mapcat f xs)`,
+/// traced with its readable arguments.
+fn apply_traced(f: &Atom, args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    let name = match f {
+        Atom::Builtin(name) => name.to_string(),
+        Atom::Lambda(lambda) => lambda.name.clone().unwrap_or_else(|| "fn".to_string()),
+        Atom::Native(native) => native.name().to_string(),
+        other => other.readable().to_string(),
+    };
+    let mut call_line = format!("({}", name);
+    for arg in &args {
+        call_line.push_str(&format!(" {}", arg.readable()));
+    }
+    call_line.push(')');
+    traced(&call_line, env, |env| call(f, args, env))
+}
+
+/// Runs `run`, writing `call_line` before it and what it returned after it
+/// to the output writer, indented by how many traced calls it's inside.
+fn traced(
+    call_line: &str,
+    env: &mut ENV,
+    run: impl FnOnce(&mut ENV) -> Result<Atom>,
+) -> Result<Atom> {
+    let indent = "  ".repeat(env.trace_depth);
+    writeln!(env.out(), "{}{}", indent, call_line).map_err(|err| EvalError::Io(err.to_string()))?;
+
+    env.trace_depth += 1;
+    let result = run(env);
+    env.trace_depth -= 1;
+    let written = match &result {
+        Ok(value) => writeln!(env.out(), "{}=> {}", indent, value.readable()),
+        Err(err) => writeln!(env.out(), "{}!! {}", indent, err),
+    };
+    written.map_err(|err| EvalError::Io(err.to_string()))?;
+    result
+}
+
+fn call(f: &Atom, args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match f {
+        Atom::Builtin(name) => call_builtin(name, args, env),
+        Atom::Lambda(lambda) => call_lambda(lambda, args, env),
+        Atom::Native(native) => call_native(native, args, env),
+        // `(:a m)` looks `:a` up in `m`, `(:a m default)` with a default
+        Atom::Keyword(_) => match args.as_slice() {
+            [coll] => Ok(get(coll, f).unwrap_or(Atom::Nil)),
+            [coll, default] => Ok(get(coll, f).unwrap_or_else(|| default.clone())),
+            _ => Err(arity(&f.to_string(), args.len())),
+        },
+        // and `(m :a)` does the same the other way round
+        Atom::Map(_) => match args.as_slice() {
+            [key] => Ok(get(f, key).unwrap_or(Atom::Nil)),
+            [key, default] => Ok(get(f, key).unwrap_or_else(|| default.clone())),
+            _ => Err(arity(&f.to_string(), args.len())),
+        },
+        other => Err(EvalError::NotCallable(other.to_string())),
+    }
+}
+
+/// The value of `key` in a map, `key` itself if it's in a set, or the element
+/// at index `key` of a vector. Anything else has no keys.
+fn get(coll: &Atom, key: &Atom) -> Option<Atom> {
+    match coll {
+        Atom::Map(map) => map.get(key).cloned(),
+        Atom::Set(set) => set.get(key).cloned(),
+        Atom::Vector(items) => match key {
+            Atom::Int(i) => usize::try_from(*i).ok().and_then(|i| items.get(i).cloned()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn call_native(native: &Native, args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match native {
+        Native::Juxt(fs) => fs
+            .iter()
+            .map(|f| apply(f, args.clone(), env))
+            .collect::<Result<Vec<Atom>>>()
+            .map(Atom::vector),
+        Native::Partial(f, captured) => {
+            let mut captured = captured.clone();
+            captured.extend(args);
+            apply(f, captured, env)
+        }
+        // `comp` never makes one of these without any functions
+        Native::Comp(fs) => {
+            let (last, rest) = fs.split_last().unwrap();
+            let init = apply(last, args, env)?;
+            rest.iter()
+                .rev()
+                .try_fold(init, |result, f| apply(f, vec![result], env))
+        }
+        Native::Constantly(value) => Ok(value.clone()),
+        Native::Host(host) => (host.f)(&args),
+        Native::Memoize(f, cache) => {
+            if let Some(result) = cache.borrow().get(&args) {
+                return Ok(result.clone());
+            }
+            // the cache isn't borrowed during the call, so `f` can call
+            // itself through the memoized function
+            let result = apply(f, args.clone(), env)?;
+            cache.borrow_mut().insert(args, result.clone());
+            Ok(result)
+        }
+    }
+}
+
+fn call_lambda(lambda: &Rc<Lambda>, args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    let arity_ok = match lambda.rest {
+        Some(_) => args.len() >= lambda.params.len(),
+        None => args.len() == lambda.params.len(),
+    };
+    if !arity_ok {
+        return Err(arity(&Atom::Lambda(lambda.clone()).to_string(), args.len()));
+    }
+
+    let mut scope = lambda.scope.clone();
+    if let Some(name) = &lambda.name {
+        scope = scope.bind(name, Atom::Lambda(lambda.clone()));
+    }
+    let mut args = args.into_iter();
+    let mut inner = scope.clone();
+    for param in &lambda.params {
+        inner = bind(param, args.next().unwrap(), &inner)?;
+    }
+    if let Some(rest) = &lambda.rest {
+        let rest_args: Vec<Atom> = args.collect();
+        let rest_args = if rest_args.is_empty() {
+            Atom::Nil
+        } else {
+            Atom::list(rest_args)
+        };
+        inner = bind(rest, rest_args, &inner)?;
+    }
+
+    loop {
+        match eval_body(&lambda.body, env, &inner) {
+            // like clojure, `recur` passes the rest parameter as one value
+            Err(EvalError::Recur(values)) => {
+                let expected = lambda.params.len() + lambda.rest.iter().len();
+                if values.len() != expected {
+                    return Err(arity("recur", values.len()));
+                }
+                inner = scope.clone();
+                let params = lambda.params.iter().chain(&lambda.rest);
+                for (param, value) in params.zip(values) {
+                    inner = bind(param, value, &inner)?;
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Binds `value` to the binding form `pattern`, returning the extended scope.
+fn bind(pattern: &Atom, value: Atom, scope: &Scope) -> Result<Scope> {
+    match pattern {
+        Atom::Symbol(name) => Ok(scope.bind(name, value)),
+        other => Err(EvalError::Malformed(format!(
+            "unsupported binding form: {}",
+            other
+        ))),
+    }
+}
+
+/// `args` printed readably and joined with spaces, for `pr` and friends.
+fn pr_str(args: &[Atom]) -> String {
+    let text: Vec<String> = args.iter().map(|arg| arg.readable().to_string()).collect();
+    text.join(" ")
+}
+
+/// The elements of a collection, in iteration order. Map entries come out as
+/// `[key value]` vectors and `nil` is the empty collection.
+fn seq_items(coll: &Atom) -> Result<Vec<Atom>> {
+    match coll {
+        Atom::Nil => Ok(vec![]),
+        Atom::List(items) | Atom::Vector(items) => Ok(items.to_vec()),
+        Atom::Set(set) => Ok(set.iter().cloned().collect()),
+        Atom::Map(map) => Ok(map
+            .iter()
+            .map(|(key, value)| Atom::vector(vec![key.clone(), value.clone()]))
+            .collect()),
+        other => Err(type_error("collection", other)),
+    }
+}
+
+/// Pushes the items onto `flat`, with the items of any list or vector among
+/// them pushed in their place, all the way down. Maps and sets stay whole.
+fn flatten(items: &[Atom], flat: &mut Vec<Atom>) {
+    for item in items {
+        match item {
+            Atom::List(inner) | Atom::Vector(inner) => flatten(inner, flat),
+            item => flat.push(item.clone()),
+        }
+    }
+}
+
+fn arity(name: &str, got: usize) -> EvalError {
+    EvalError::Arity {
+        name: name.to_string(),
+        got,
+    }
+}
+
+fn type_error(expected: &'static str, got: &Atom) -> EvalError {
+    EvalError::Type {
+        expected,
+        got: got.type_name().to_string(),
+    }
+}
+
+fn malformed(form: &str, message: &str) -> EvalError {
+    EvalError::Malformed(format!("{}: {}", form, message))
+}
+
+/// Names that are always bound to a number.
+pub(crate) const CONSTANTS: &[(&str, f64)] = &[("pi", std::f64::consts::PI)];
+
+/// Every name `special_form` handles.
+pub(crate) const SPECIAL_FORMS: &[&str] = &[
+    "quote",
+    "def",
+    "defonce",
+    "defn",
+    "defmacro",
+    "fn",
+    "if",
+    "do",
+    "let",
+    "doseq",
+    "for",
+    "assert",
+    "case",
+    "loop",
+    "recur",
+    "while",
+    "with-out-str",
+    "declare",
+    "if-let",
+    "when-let",
+];
+
+fn special_form(head: &str, args: &[Atom], env: &mut ENV, scope: &Scope) -> Option<Result<Atom>> {
+    let result = match head {
+        "quote" => eval_quote(args),
+        "def" => eval_def(args, env, scope),
+        "defonce" => eval_defonce(args, env, scope),
+        "defn" => eval_defn(args, env, scope),
+        "defmacro" => eval_defmacro(args, env, scope),
+        "fn" => eval_fn(args, scope),
+        "if" => eval_if(args, env, scope),
+        "do" => eval_body(args, env, scope),
+        "let" => eval_let(args, env, scope),
+        "doseq" => eval_doseq(args, env, scope),
+        "for" => eval_for(args, env, scope),
+        "assert" => eval_assert(args, env, scope),
+        "case" => eval_case(args, env, scope),
+        "loop" => eval_loop(args, env, scope),
+        "while" => eval_while(args, env, scope),
+        "with-out-str" => eval_with_out_str(args, env, scope),
+        "declare" => eval_declare(args, env),
+        "if-let" | "when-let" => eval_if_let(head, args, env, scope),
+        "recur" => eval_all(args, env, scope).and_then(|values| Err(EvalError::Recur(values))),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn eval_quote(args: &[Atom]) -> Result<Atom> {
+    match args {
+        [form] => Ok(form.clone()),
+        _ => Err(malformed("quote", "expected exactly one form")),
+    }
+}
+
+fn eval_def(args: &[Atom], env: &mut ENV, scope: &Scope) -> Result<Atom> {
+    match args {
+        [Atom::Symbol(name), value] => {
+            let value = eval_form(value, env, scope)?;
+            define(name, value, env)?;
+            Ok(Atom::Symbol(name.clone()))
+        }
+        _ => Err(malformed("def", "expected a symbol and a value")),
+    }
+}
+
+/// Binds a top level symbol, warning on the output writer when that replaces
+/// an existing definition since that's usually an accident.
+fn define(name: &str, value: Atom, env: &mut ENV) -> Result<()> {
+    if env.lookup(name).is_some() {
+        writeln!(
+            env.out(),
+            "WARNING: {} is already defined, redefining it",
+            name
+        )
+        .map_err(|err| EvalError::Io(err.to_string()))?;
+    }
+    env.define(name, value);
+    Ok(())
+}
+
+/// Like `def`, but leaves an existing binding alone without evaluating the
+/// value at all.
+fn eval_defonce(args: &[Atom], env: &mut ENV, scope: &Scope) -> Result<Atom> {
+    match args {
+        [Atom::Symbol(name), _] if env.lookup(name).is_some() => Ok(Atom::Symbol(name.clone())),
+        [Atom::Symbol(_), _] => eval_def(args, env, scope),
+        _ => Err(malformed("defonce", "expected a symbol and a value")),
+    }
+}
+
+fn eval_defn(args: &[Atom], env: &mut ENV, scope: &Scope) -> Result<Atom> {
+    match args {
+        [Atom::Symbol(name), rest @ ..] => {
+            let (doc, rest) = docstring(rest);
+            if doc.is_some() && rest.is_empty() {
+                return Err(malformed(
+                    "defn",
+                    "expected a parameter vector after the docstring",
+                ));
+            }
+            let lambda = make_lambda(Some(name.clone()), doc, rest, scope)?;
+            define(name, lambda, env)?;
+            Ok(Atom::Symbol(name.clone()))
+        }
+        _ => Err(malformed("defn", "expected a name")),
+    }
+}
+
+fn eval_defmacro(args: &[Atom], env: &mut ENV, scope: &Scope) -> Result<Atom> {
+    match args {
+        [Atom::Symbol(name), rest @ ..] => {
+            let (doc, rest) = docstring(rest);
+            let lambda = match make_lambda(Some(name.clone()), doc, rest, scope)? {
+                Atom::Lambda(lambda) => lambda,
+                _ => unreachable!("make_lambda always makes a lambda"),
+            };
+            define(name, Atom::Macro(lambda), env)?;
+            Ok(Atom::Symbol(name.clone()))
+        }
+        _ => Err(malformed("defmacro", "expected a name")),
+    }
+}
+
+/// Splits the optional docstring off the front of what follows a name in a
+/// definition. Only a string *before* the parameters is a docstring; in
+/// `(defn f [] "s")` the string is the body.
+pub(crate) fn docstring(args: &[Atom]) -> (Option<String>, &[Atom]) {
+    match args {
+        [Atom::String(doc), rest @ ..] => (Some(doc.to_string()), rest),
+        _ => (None, args),
+    }
+}
+
+fn eval_fn(args: &[Atom], scope: &Scope) -> Result<Atom> {
+    match args {
+        [Atom::Symbol(name), rest @ ..] => make_lambda(Some(name.clone()), None, rest, scope),
+        _ => make_lambda(None, None, args, scope),
+    }
+}
+
+/// Builds a function from `[params] body...`.
+fn make_lambda(
+    name: Option<String>,
+    doc: Option<String>,
+    args: &[Atom],
+    scope: &Scope,
+) -> Result<Atom> {
+    let (params, body) = match args {
+        [Atom::Vector(params), body @ ..] => (params, body),
+        _ => return Err(malformed("fn", "expected a parameter vector")),
+    };
+
+    let mut fixed = vec![];
+    let mut rest = None;
+    let mut params = params.iter();
+    while let Some(param) = params.next() {
+        if *param == Atom::Symbol("&".to_string()) {
+            rest = match (params.next(), params.next()) {
+                (Some(rest), None) => Some(rest.clone()),
+                _ => return Err(malformed("fn", "expected one parameter after &")),
+            };
+        } else {
+            fixed.push(param.clone());
+        }
+    }
+
+    Ok(Atom::Lambda(Rc::new(Lambda {
+        name,
+        doc,
+        params: fixed,
+        rest,
+        body: body.to_vec(),
+        scope: scope.clone(),
+    })))
+}
+
+fn eval_if(args: &[Atom], env: &mut ENV, scope: &Scope) -> Result<Atom> {
+    let (test, then, otherwise) = match args {
+        [test, then] => (test, then, None),
+        [test, then, otherwise] => (test, then, Some(otherwise)),
+        _ => {
+            return Err(malformed(
+                "if",
+                "expected a test, a then and an optional else",
+            ))
+        }
+    };
+    if eval_form(test, env, scope)?.is_truthy() {
+        eval_form(then, env, scope)
+    } else if let Some(otherwise) = otherwise {
+        eval_form(otherwise, env, scope)
+    } else {
+        Ok(Atom::Nil)
+    }
+}
+
+/// `(case x 1 "one" (2 3) "two or three" "other")`. The tests aren't
+/// evaluated, and a list of them matches any one. A lone last form is the
+/// default.
+fn eval_case(args: &[Atom], env: &mut ENV, scope: &Scope) -> Result<Atom> {
+    let (value, clauses) = match args.split_first() {
+        Some(split) => split,
+        None => return Err(malformed("case", "expected a value to dispatch on")),
+    };
+    let (pairs, default) = if clauses.len() % 2 == 0 {
+        (clauses, None)
+    } else {
+        (&clauses[..clauses.len() - 1], clauses.last())
+    };
+
+    let mut seen = HashSet::new();
+    for pair in pairs.chunks(2) {
+        let tests = match &pair[0] {
+            Atom::List(tests) => tests.as_slice(),
+            test => std::slice::from_ref(test),
+        };
+        for test in tests {
+            if !seen.insert(test) {
+                return Err(malformed(
+                    "case",
+                    &format!("duplicate test constant: {}", test.readable()),
+                ));
+            }
+        }
+    }
+
+    let value = eval_form(value, env, scope)?;
+    for pair in pairs.chunks(2) {
+        let matches = match &pair[0] {
+            Atom::List(tests) => tests.contains(&value),
+            test => *test == value,
+        };
+        if matches {
+            return eval_form(&pair[1], env, scope);
+        }
+    }
+    match default {
+        Some(default) => eval_form(default, env, scope),
+        None => Err(EvalError::NoMatchingClause(value.readable().to_string())),
+    }
+}
+
+/// `(if-let [name expr] then else?)` and `(when-let [name expr] body...)`:
+/// `if` and `when` on the value of `expr`, with `name` bound to it in the
+/// branch taken when it's truthy. The else branch doesn't see the binding.
+fn eval_if_let(head: &str, args: &[Atom], env: &mut ENV, scope: &Scope) -> Result<Atom> {
+    let (pattern, expr, rest) = match args {
+        [Atom::Vector(binding), rest @ ..] if binding.len() == 2 => {
+            (&binding[0], &binding[1], rest)
+        }
+        _ => return Err(malformed(head, "expected a vector of one binding pair")),
+    };
+    let (then, otherwise) = match (head, rest) {
+        ("when-let", body) => (body, None),
+        (_, [then]) => (std::slice::from_ref(then), None),
+        (_, [then, otherwise]) => (std::slice::from_ref(then), Some(otherwise)),
+        _ => {
+            return Err(malformed(
+                head,
+                "expected a binding, a then and an optional else",
+            ))
+        }
+    };
+    let value = eval_form(expr, env, scope)?;
+    if value.is_truthy() {
+        let scope = bind(pattern, value, scope)?;
+        eval_body(then, env, &scope)
+    } else if let Some(otherwise) = otherwise {
+        eval_form(otherwise, env, scope)
+    } else {
+        Ok(Atom::Nil)
+    }
+}
+
+/// `(let [name value...] body...)`. The bindings are made one at a time, so
+/// each value sees the names bound before it, and a later binding of a name
+/// shadows an earlier one.
+fn eval_let(args: &[Atom], env: &mut ENV, scope: &Scope) -> Result<Atom> {
+    let (bindings, body) = match args {
+        [Atom::Vector(bindings), body @ ..] if bindings.len() % 2 == 0 => (bindings, body),
+        _ => return Err(malformed("let", "expected a vector of binding pairs")),
+    };
+    let mut scope = scope.clone();
+    for pair in bindings.chunks(2) {
+        let value = eval_form(&pair[1], env, &scope)?;
+        scope = bind(&pair[0], value, &scope)?;
+    }
+    eval_body(body, env, &scope)
+}
+
+/// `(loop [bindings...] body...)`: a `let` that `recur` in the body jumps
+/// back to, rebinding the names to new values.
+fn eval_loop(args: &[Atom], env: &mut ENV, scope: &Scope) -> Result<Atom> {
+    let (bindings, body) = match args {
+        [Atom::Vector(bindings), body @ ..] if bindings.len() % 2 == 0 => (bindings, body),
+        _ => return Err(malformed("loop", "expected a vector of binding pairs")),
+    };
+    let patterns: Vec<&Atom> = bindings.iter().step_by(2).collect();
+    let mut inner = scope.clone();
+    for pair in bindings.chunks(2) {
+        let value = eval_form(&pair[1], env, &inner)?;
+        inner = bind(&pair[0], value, &inner)?;
+    }
+    loop {
+        match eval_body(body, env, &inner) {
+            Err(EvalError::Recur(values)) => {
+                if values.len() != patterns.len() {
+                    return Err(arity("recur", values.len()));
+                }
+                inner = scope.clone();
+                for (pattern, value) in patterns.iter().zip(values) {
+                    inner = bind(pattern, value, &inner)?;
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
+/// `(while test body...)`, running the body for as long as the test is
+/// truthy.
+fn eval_while(args: &[Atom], env: &mut ENV, scope: &Scope) -> Result<Atom> {
+    let (test, body) = match args.split_first() {
+        Some(split) => split,
+        None => return Err(malformed("while", "expected a test")),
+    };
+    while eval_form(test, env, scope)?.is_truthy() {
+        eval_body(body, env, scope)?;
+    }
+    Ok(Atom::Nil)
+}
+
+/// `(declare name...)`, promising definitions for names that are used
+/// before they're defined. Using one before then is an `Undefined` error
+/// rather than `Unbound`.
+fn eval_declare(args: &[Atom], env: &mut ENV) -> Result<Atom> {
+    for arg in args {
+        match arg {
+            Atom::Symbol(name) => {
+                env.declared.insert(name.clone());
+            }
+            other => {
+                return Err(malformed(
+                    "declare",
+                    &format!("expected a symbol, got {}", other),
+                ))
+            }
+        }
+    }
+    Ok(Atom::Nil)
+}
+
+/// `(with-out-str body...)`, running the body and returning what it printed
+/// as a string instead of its value.
+fn eval_with_out_str(args: &[Atom], env: &mut ENV, scope: &Scope) -> Result<Atom> {
+    let (result, output) = env.capture_output(|env| eval_body(args, env, scope));
+    result?;
+    Ok(Atom::string(String::from_utf8_lossy(&output)))
+}
+
+/// Rejects any `recur` in `form` that isn't in tail position of its `loop`
+/// or `fn`, where jumping back would skip the rest of the work. `tail` is
+/// whether `form` itself is in tail position. Macro calls aren't expanded
+/// here, so `recur` inside one isn't checked.
+fn check_recur(form: &Atom, tail: bool, env: &ENV) -> Result<()> {
+    let items = match form {
+        Atom::List(items) if !items.is_empty() => items,
+        Atom::Vector(items) => return check_all(items, env),
+        Atom::Map(map) => {
+            for (key, value) in map.iter() {
+                check_recur(key, false, env)?;
+                check_recur(value, false, env)?;
+            }
+            return Ok(());
+        }
+        Atom::Set(set) => {
+            for item in set.iter() {
+                check_recur(item, false, env)?;
+            }
+            return Ok(());
+        }
+        _ => return Ok(()),
+    };
+    let head = match &items[0] {
+        Atom::Symbol(head) => head.as_str(),
+        _ => return check_all(items, env),
+    };
+    let args = &items[1..];
+    match head {
+        _ if matches!(env.lookup(head), Some(Atom::Macro(_))) => Ok(()),
+        // nothing quoted runs, and what a syntax-quote expands to isn't
+        // checked any more than a macro expansion is
+        "quote" | "syntax-quote" => Ok(()),
+        "recur" if !tail => Err(malformed(
+            "recur",
+            "can only be used in tail position of a loop or fn",
+        )),
+        "recur" => check_all(args, env),
+        "if" => match args.split_first() {
+            Some((test, branches)) => {
+                check_recur(test, false, env)?;
+                branches
+                    .iter()
+                    .try_for_each(|branch| check_recur(branch, tail, env))
+            }
+            None => Ok(()),
+        },
+        "do" => check_body(args, tail, env),
+        "if-let" | "when-let" => match args.split_first() {
+            Some((Atom::Vector(binding), rest)) => {
+                check_all(binding, env)?;
+                if head == "when-let" {
+                    check_body(rest, tail, env)
+                } else {
+                    rest.iter()
+                        .try_for_each(|branch| check_recur(branch, tail, env))
+                }
+            }
+            _ => check_all(args, env),
+        },
+        "let" | "loop" => match args.split_first() {
+            Some((Atom::Vector(bindings), body)) => {
+                check_all(bindings, env)?;
+                // a loop body is a new place to recur to
+                check_body(body, tail || head == "loop", env)
+            }
+            _ => check_all(args, env),
+        },
+        "fn" | "defn" | "defmacro" => {
+            let body = args
+                .iter()
+                .position(|arg| matches!(arg, Atom::Vector(_)))
+                .map_or(&[][..], |params| &args[params + 1..]);
+            check_body(body, true, env)
+        }
+        "case" => match args.split_first() {
+            Some((value, clauses)) => {
+                check_recur(value, false, env)?;
+                for (i, clause) in clauses.iter().enumerate() {
+                    let is_test = i % 2 == 0 && i + 1 < clauses.len();
+                    if !is_test {
+                        check_recur(clause, tail, env)?;
+                    }
+                }
+                Ok(())
+            }
+            None => Ok(()),
+        },
+        _ => check_all(args, env),
+    }
+}
+
+/// Checks every form in `forms`, none of them in tail position.
+fn check_all(forms: &[Atom], env: &ENV) -> Result<()> {
+    forms
+        .iter()
+        .try_for_each(|form| check_recur(form, false, env))
+}
+
+/// Checks a body where only the last form is in tail position.
+fn check_body(body: &[Atom], tail: bool, env: &ENV) -> Result<()> {
+    match body.split_last() {
+        Some((last, rest)) => {
+            check_all(rest, env)?;
+            check_recur(last, tail, env)
+        }
+        None => Ok(()),
+    }
+}
+
+/// `(assert expr message?)`. Does nothing, not even evaluating `expr`, when
+/// `ENV::assertions` is off.
+fn eval_assert(args: &[Atom], env: &mut ENV, scope: &Scope) -> Result<Atom> {
+    let (test, message) = match args {
+        [test] => (test, None),
+        [test, message] => (test, Some(message)),
+        _ => {
+            return Err(malformed(
+                "assert",
+                "expected a test and an optional message",
+            ))
+        }
+    };
+    if !env.assertions || eval_form(test, env, scope)?.is_truthy() {
+        return Ok(Atom::Nil);
+    }
+    let message = match message {
+        Some(message) => Some(eval_form(message, env, scope)?.to_string()),
+        None => None,
+    };
+    Err(EvalError::Assertion {
+        message,
+        form: test.readable().to_string(),
+    })
+}
+
+fn eval_doseq(args: &[Atom], env: &mut ENV, scope: &Scope) -> Result<Atom> {
+    let (bindings, body) = comprehension("doseq", args)?;
+    each_binding(bindings, env, scope, &mut |env, scope| {
+        eval_body(body, env, scope)?;
+        Ok(())
+    })?;
+    Ok(Atom::Nil)
+}
+
+fn eval_for(args: &[Atom], env: &mut ENV, scope: &Scope) -> Result<Atom> {
+    let (bindings, body) = comprehension("for", args)?;
+    let body = match body {
+        [body] => body,
+        _ => return Err(malformed("for", "expected a single body expression")),
+    };
+    let mut results = vec![];
+    each_binding(bindings, env, scope, &mut |env, scope| {
+        results.push(eval_form(body, env, scope)?);
+        Ok(())
+    })?;
+    Ok(Atom::vector(results))
+}
+
+fn comprehension<'a>(form: &str, args: &'a [Atom]) -> Result<(&'a [Atom], &'a [Atom])> {
+    match args {
+        [Atom::Vector(bindings), body @ ..] if !bindings.is_empty() && bindings.len() % 2 == 0 => {
+            Ok((bindings, body))
+        }
+        _ => Err(malformed(form, "expected a vector of binding pairs")),
+    }
+}
+
+/// Runs `f` once for every combination of the `[name coll ...]` binding
+/// pairs, the rightmost binding varying fastest. Each collection is
+/// evaluated with the bindings to its left in scope.
+fn each_binding(
+    bindings: &[Atom],
+    env: &mut ENV,
+    scope: &Scope,
+    f: &mut dyn FnMut(&mut ENV, &Scope) -> Result<()>,
+) -> Result<()> {
+    let (pattern, coll, rest) = match bindings {
+        [] => return f(env, scope),
+        [pattern, coll, rest @ ..] => (pattern, coll, rest),
+        _ => unreachable!("binding vectors are checked to have an even length"),
+    };
+    let coll = eval_form(coll, env, scope)?;
+    for item in seq_items(&coll)? {
+        let scope = bind(pattern, item, scope)?;
+        each_binding(rest, env, &scope, f)?;
+    }
+    Ok(())
+}
+
+/// Every builtin function, with the description `doc` shows for it.
+pub(crate) const BUILTINS: &[(&str, &str)] = &[
+    ("+", "Returns the sum of nums. (+) returns 0."),
+    ("-", "If no ys are supplied, returns the negation of x, else subtracts the ys from x."),
+    ("*", "Returns the product of nums. (*) returns 1."),
+    ("/", "If no denominators are supplied, returns 1/numerator, else divides numerator by the denominators."),
+    ("=", "Returns true if all arguments are equal."),
+    ("<", "Returns true if nums are in monotonically increasing order."),
+    (">", "Returns true if nums are in monotonically decreasing order."),
+    ("<=", "Returns true if nums are in monotonically non-decreasing order."),
+    (">=", "Returns true if nums are in monotonically non-increasing order."),
+    ("not", "Returns true if x is logical false, false otherwise."),
+    ("inc", "Returns a number one greater than num."),
+    ("dec", "Returns a number one less than num."),
+    ("print", "Prints the args, separated by spaces."),
+    ("println", "Same as print followed by a newline."),
+    ("pr", "Prints the args readably, separated by spaces: strings are quoted and escaped so the output reads back as the same values."),
+    ("prn", "Same as pr followed by a newline."),
+    ("pr-str", "Returns the args printed readably, as by pr, as a string."),
+    ("list", "Creates a new list containing the items."),
+    ("vector", "Creates a new vector containing the items."),
+    ("conj", "Returns a new collection with the xs added where the collection adds them most cheaply."),
+    ("disj", "Returns a new set that does not contain the keys."),
+    ("contains?", "Returns true if key is present in the collection. For vectors and strings key is an index."),
+    ("keys", "Returns a vector of the map's keys."),
+    ("vals", "Returns a vector of the map's values, in the same order as keys."),
+    ("merge", "Returns a map of the maps conj-ed onto the first, later keys winning. Returns nil for no maps."),
+    ("format", "Formats a string using %d, %s, %f and %%."),
+    ("not=", "Same as (not (= obj1 obj2))."),
+    ("save-env", "Saves the top level bindings that can be saved to a file."),
+    ("doc", "Prints the documentation for a function."),
+    ("macroexpand-1", "Expands form once if it is a macro call, otherwise returns it unchanged."),
+    ("macroexpand", "Expands form repeatedly until it is no longer a macro call."),
+    ("juxt", "Takes a set of functions and returns a fn that is the juxtaposition of those fns. ((juxt a b c) x) returns [(a x) (b x) (c x)]."),
+    ("partial", "Takes a function f and fewer than the normal arguments to f, and returns a fn that takes a variable number of additional args. When called, the returned function calls f with args + additional args."),
+    ("apply", "Applies fn f to the argument list formed by prepending intervening arguments to args."),
+    ("identity", "Returns its argument."),
+    ("comp", "Takes a set of functions and returns a fn that is the composition of those fns. The returned fn takes a variable number of args, applies the rightmost of fns to the args, the next fn (right-to-left) to the result, etc. (comp) returns identity."),
+    ("count", "Returns the number of items in the collection. (count nil) returns 0. For a string, counts the characters, not the bytes."),
+    ("empty?", "Returns true if coll has no items."),
+    ("seq", "Returns nil if the collection is empty, else the collection itself, so (if (seq coll) ...) tests for a non-empty collection."),
+    ("into", "Returns a new coll consisting of to with all of the items of from conjoined."),
+    ("sort", "Returns a sorted vector of the items in coll. Numbers sort by value, strings and keywords lexicographically."),
+    ("sort-by", "Returns a sorted vector of the items in coll, where the sort order is determined by comparing (keyfn item)."),
+    ("reverse", "Returns the items of coll in reverse order: a list for a list, a string of the reversed characters for a string and a vector otherwise."),
+    ("constantly", "Returns a function that takes any number of arguments and returns x."),
+    ("vec", "Creates a new vector containing the contents of coll."),
+    ("set", "Returns a set of the distinct elements of coll."),
+    ("frequencies", "Returns a map from distinct items in coll to the number of times they appear."),
+    ("group-by", "Returns a map of the elements of coll keyed by the result of f on each element. The value at each key is a vector of the corresponding elements, in the order they appeared in coll."),
+    ("even?", "Returns true if n is even."),
+    ("odd?", "Returns true if n is odd."),
+    ("re-pattern", "Returns an instance of a regex for the string s."),
+    ("re-matches", "Returns the match, if any, of string to pattern. The whole string has to match. With groups, returns a vector of the match and each group."),
+    ("re-find", "Returns the leftmost match, if any, of string to pattern. With groups, returns a vector of the match and each group."),
+    ("atom", "Creates and returns an atom with an initial value of x."),
+    ("deref", "Returns the current value of an atom. @a reads as (deref a)."),
+    ("reset!", "Sets the value of atom to newval without regard for the current value. Returns newval."),
+    ("swap!", "Atomically swaps the value of atom to be (apply f current-value-of-atom args). Returns the value that was swapped in."),
+    ("peek", "For a list, same as first, for a vector, same as, but much more efficient than, last. Returns nil if the collection is empty."),
+    ("pop", "For a list, returns a new list without the first item, for a vector, returns a new vector without the last item. Popping an empty list or vector is an error."),
+    ("take", "Returns a vector of the first n items of coll, or all of them if there are fewer than n."),
+    ("drop", "Returns a vector of all but the first n items of coll."),
+    ("take-while", "Returns a vector of the items of coll up to the first one for which (pred item) is falsey."),
+    ("drop-while", "Returns a vector of the items of coll starting from the first one for which (pred item) is falsey."),
+    ("zipmap", "Returns a map with the keys mapped to the corresponding vals, stopping at the shorter of the two."),
+    ("interleave", "Returns a vector of the first item in each coll, then the second etc., stopping at the shortest coll."),
+    ("distinct", "Returns a vector of the items of coll with duplicates removed, each kept where it first appears."),
+    ("exit", "Stops the program with the given exit status, 0 if there is none."),
+    ("type", "Returns a keyword naming the type of x, like :int, :string or :function."),
+    ("name", "Returns the name part of a symbol or keyword, without its namespace. Returns a string as it is."),
+    ("namespace", "Returns the namespace of a symbol or keyword, or nil if it has none."),
+    ("keyword", "Returns a keyword with the given name, and namespace if one is given."),
+    ("symbol", "Returns a symbol with the given name, and namespace if one is given."),
+    ("resolve", "Returns the value the symbol is defined as, or nil if it isn't defined. Local bindings aren't seen."),
+    ("zero?", "Returns true if n is zero."),
+    ("pos?", "Returns true if n is greater than zero."),
+    ("neg?", "Returns true if n is less than zero."),
+    ("sqrt", "Returns the square root of n as a float, NaN for a negative n."),
+    ("pow", "Returns base raised to the power of exponent as a float."),
+    ("abs", "Returns the absolute value of n."),
+    ("floor", "Returns the largest whole number not greater than n. An int stays an int."),
+    ("ceil", "Returns the smallest whole number not less than n. An int stays an int."),
+    ("round", "Returns n rounded to the nearest int, halves away from zero."),
+    ("rand", "Returns a random float between 0 (inclusive) and n (exclusive), 1 if there is no n."),
+    ("rand-int", "Returns a random int between 0 (inclusive) and n (exclusive)."),
+    ("set-seed", "Seeds the random number generator, so rand and rand-int return the same numbers each run."),
+    ("str/split", "Splits the string on every occurrence of the separator, returning a vector of strings. An empty separator splits it into characters."),
+    ("str/join", "Returns the items of coll as one string, with the separator between them if one is given. nil items are empty."),
+    ("update", "Returns the map with the value at key replaced by (f old-value args...)."),
+    ("update-in", "Returns the nested maps with the value at the path of keys replaced by (f old-value args...), making maps for keys that aren't there."),
+    ("assoc-in", "Returns the nested maps with the value at the path of keys set to v, making maps for keys that aren't there."),
+    ("get-in", "Returns the value at the path of keys through nested maps and vector indices, or default (nil if not given) if it isn't there."),
+    ("repeat", "Returns a vector of x repeated n times."),
+    ("repeatedly", "Returns a vector of the results of calling (f) n times."),
+    ("iterate", "Returns a vector of n items: x, (f x), (f (f x)) and so on."),
+    ("memoize", "Returns a function that calls f, but remembers the result for each list of arguments and returns it again instead of calling f twice."),
+    ("byte-count", "Returns the number of bytes in the string's UTF-8 encoding. count gives the number of characters."),
+    ("subs", "Returns the characters of s from start (inclusive) to end (exclusive), or to the end if there is no end."),
+    ("flatten", "Returns a vector of the items of a list or vector with every nested list and vector inlined, all the way down. Maps and sets stay whole, and anything that isn't a list or vector gives []."),
+    ("mapcat", "Returns a vector of the items of (f item) for each item of coll, one after another."),
+    ("some", "Returns the first truthy (pred item) for the items of coll, or nil if there is none."),
+    ("every?", "Returns true if (pred item) is truthy for every item of coll, which it is for an empty coll."),
+    ("concat", "Returns a vector of the items of each coll, one coll after another."),
+];
+
+fn builtin_doc(name: &str) -> Option<&'static str> {
+    BUILTINS
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, doc)| *doc)
+}
+
+/// Calls the builtin `name` from the `ENV`'s table.
+fn call_builtin(name: &'static str, args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match env.builtin(name) {
+        Some((_, f)) => f(args, env),
+        None => Err(EvalError::Unbound(name.to_string())),
+    }
+}
+
+/// Every builtin `Builtins::standard` starts with that isn't arithmetic.
+pub(crate) const CORE: &[(&str, BuiltinFn)] = &[
+    ("not", not),
+    ("even?", |args, env| parity("even?", args, env)),
+    ("odd?", |args, env| parity("odd?", args, env)),
+    ("zero?", |args, env| sign("zero?", args, env)),
+    ("pos?", |args, env| sign("pos?", args, env)),
+    ("neg?", |args, env| sign("neg?", args, env)),
+    ("sqrt", sqrt),
+    ("pow", pow),
+    ("abs", abs),
+    ("floor", |args, env| floor_or_ceil("floor", args, env)),
+    ("ceil", |args, env| floor_or_ceil("ceil", args, env)),
+    ("round", round),
+    ("rand", rand),
+    ("rand-int", rand_int),
+    ("set-seed", set_seed),
+    ("inc", inc),
+    ("dec", dec),
+    ("print", |args, env| print("print", args, env)),
+    ("println", |args, env| print("println", args, env)),
+    ("pr", |args, env| pr("pr", args, env)),
+    ("prn", |args, env| pr("prn", args, env)),
+    ("pr-str", |args, _| Ok(Atom::string(pr_str(&args)))),
+    ("list", |args, _| Ok(Atom::list(args))),
+    ("vector", |args, _| Ok(Atom::vector(args))),
+    ("conj", conj_coll),
+    ("type", type_of),
+    ("name", |args, env| name_part("name", args, env)),
+    ("namespace", |args, env| name_part("namespace", args, env)),
+    ("keyword", |args, env| named("keyword", args, env)),
+    ("symbol", |args, env| named("symbol", args, env)),
+    ("resolve", resolve_symbol),
+    ("exit", exit),
+    ("peek", peek),
+    ("pop", pop_coll),
+    ("repeat", repeat),
+    ("repeatedly", repeatedly),
+    ("iterate", iterate),
+    ("take", |args, env| take_or_drop("take", args, env)),
+    ("drop", |args, env| take_or_drop("drop", args, env)),
+    ("take-while", |args, env| {
+        take_or_drop_while("take-while", args, env)
+    }),
+    ("drop-while", |args, env| {
+        take_or_drop_while("drop-while", args, env)
+    }),
+    ("some", some),
+    ("every?", every),
+    ("concat", concat),
+    ("mapcat", mapcat),
+    ("flatten", flatten_nested),
+    ("distinct", distinct),
+    ("update", |args, env| update("update", args, env)),
+    ("update-in", |args, env| update("update-in", args, env)),
+    ("get-in", get_in),
+    ("assoc-in", assoc_in),
+    ("zipmap", zipmap),
+    ("interleave", interleave),
+    ("sort", sort_coll),
+    ("sort-by", sort_by),
+    ("reverse", reverse),
+    ("vec", vec),
+    ("set", set),
+    ("frequencies", frequencies),
+    ("group-by", group_by),
+    ("into", into),
+    ("disj", disj),
+    ("keys", |args, env| keys_or_vals("keys", args, env)),
+    ("vals", |args, env| keys_or_vals("vals", args, env)),
+    ("merge", merge),
+    ("format", format_template),
+    ("doc", doc),
+    ("macroexpand-1", macroexpand_form_1),
+    ("macroexpand", macroexpand_form),
+    ("save-env", save_bindings),
+    ("juxt", juxt),
+    ("partial", partial),
+    ("identity", identity),
+    ("constantly", constantly),
+    ("memoize", memoize),
+    ("comp", comp),
+    ("apply", apply_spread),
+    ("count", count_coll),
+    ("empty?", is_empty),
+    ("seq", seq),
+    ("atom", atom),
+    ("deref", deref),
+    ("reset!", reset),
+    ("swap!", swap),
+    ("re-pattern", re_pattern),
+    ("byte-count", byte_count),
+    ("subs", subs),
+    ("str/split", str_split),
+    ("str/join", str_join),
+    ("re-matches", |args, env| re_match("re-matches", args, env)),
+    ("re-find", |args, env| re_match("re-find", args, env)),
+    ("contains?", contains_key),
+];
+
+fn not(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [value] => Ok(Atom::Boolean(!value.is_truthy())),
+        _ => Err(arity("not", args.len())),
+    }
+}
+
+fn parity(name: &str, args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::Int(n)] => Ok(Atom::Boolean((n % 2 == 0) == (name == "even?"))),
+        [other] => Err(type_error("int", other)),
+        _ => Err(arity(name, args.len())),
+    }
+}
+
+fn sign(name: &str, args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [n] => {
+            let n = number(n)?;
+            Ok(Atom::Boolean(match name {
+                "zero?" => n == 0.0,
+                "pos?" => n > 0.0,
+                _ => n < 0.0,
+            }))
+        }
+        _ => Err(arity(name, args.len())),
+    }
+}
+
+// like java's Math, sqrt and pow work in floats, so domain errors give NaN:
+// (sqrt -1) and (pow -8 1/3) are NaN rather than errors
+fn sqrt(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [n] => Ok(Atom::Float(number(n)?.sqrt())),
+        _ => Err(arity("sqrt", args.len())),
+    }
+}
+
+fn pow(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [base, exponent] => Ok(Atom::Float(number(base)?.powf(number(exponent)?))),
+        _ => Err(arity("pow", args.len())),
+    }
+}
+
+fn abs(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::Int(n)] => n.checked_abs().map(Atom::Int).ok_or(EvalError::Overflow),
+        [Atom::Ratio(n, d)] => Ok(Atom::Ratio(n.checked_abs().ok_or(EvalError::Overflow)?, *d)),
+        [n] => Ok(Atom::Float(number(n)?.abs())),
+        _ => Err(arity("abs", args.len())),
+    }
+}
+
+fn floor_or_ceil(name: &str, args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::Int(n)] => Ok(Atom::Int(*n)),
+        [n] if name == "floor" => Ok(Atom::Float(number(n)?.floor())),
+        [n] => Ok(Atom::Float(number(n)?.ceil())),
+        _ => Err(arity(name, args.len())),
+    }
+}
+
+// rounds half away from zero, to an int
+fn round(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::Int(n)] => Ok(Atom::Int(*n)),
+        [n] => {
+            let rounded = number(n)?.round();
+            if rounded.is_finite() && rounded.abs() < i64::MAX as f64 {
+                Ok(Atom::Int(rounded as i64))
+            } else {
+                Err(EvalError::Overflow)
+            }
+        }
+        _ => Err(arity("round", args.len())),
+    }
+}
+
+fn rand(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    // 53 random bits, the precision of a float, scaled into [0, 1)
+    let unit = (env.next_random() >> 11) as f64 / (1u64 << 53) as f64;
+    match args.as_slice() {
+        [] => Ok(Atom::Float(unit)),
+        [n] => Ok(Atom::Float(unit * number(n)?)),
+        _ => Err(arity("rand", args.len())),
+    }
+}
+
+fn rand_int(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::Int(n)] if *n > 0 => {
+            // the high bits of random * n: spread over [0, n), and only
+            // noticeably uneven for an n near i64::MAX
+            let scaled = (env.next_random() as u128 * *n as u128) >> 64;
+            Ok(Atom::Int(scaled as i64))
+        }
+        [other] => Err(type_error("positive int", other)),
+        _ => Err(arity("rand-int", args.len())),
+    }
+}
+
+fn set_seed(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::Int(seed)] => {
+            env.seed(*seed as u64);
+            Ok(Atom::Nil)
+        }
+        [other] => Err(type_error("int", other)),
+        _ => Err(arity("set-seed", args.len())),
+    }
+}
+
+fn inc(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [value] => add(vec![value.clone(), Atom::Int(1)], env),
+        _ => Err(arity("inc", args.len())),
+    }
+}
+
+fn dec(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [value] => subtract(vec![value.clone(), Atom::Int(1)], env),
+        _ => Err(arity("dec", args.len())),
+    }
+}
+
+fn print(name: &str, args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    let text: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    let mut text = text.join(" ");
+    if name == "println" {
+        text.push('\n');
+    }
+    env.out()
+        .write_all(text.as_bytes())
+        .map_err(|err| EvalError::Io(err.to_string()))?;
+    Ok(Atom::Nil)
+}
+
+fn pr(name: &str, args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    let mut text = pr_str(&args);
+    if name == "prn" {
+        text.push('\n');
+    }
+    env.out()
+        .write_all(text.as_bytes())
+        .map_err(|err| EvalError::Io(err.to_string()))?;
+    Ok(Atom::Nil)
+}
+
+fn conj_coll(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    // hand conj the collection itself rather than a clone, so a
+    // collection nothing else holds is added to in place
+    let mut args = args.into_iter();
+    match args.next() {
+        Some(coll) => conj(coll, args.collect()),
+        None => Err(arity("conj", 0)),
+    }
+}
+
+fn type_of(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [x] => Ok(Atom::Keyword(x.type_name().to_string())),
+        _ => Err(arity("type", args.len())),
+    }
+}
+
+fn name_part(name: &str, args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::String(s)] if name == "name" => Ok(Atom::String(s.clone())),
+        [Atom::Symbol(s) | Atom::Keyword(s)] => {
+            let (namespace, local) = split_namespace(s);
+            if name == "name" {
+                Ok(Atom::string(local))
+            } else {
+                Ok(namespace.map_or(Atom::Nil, Atom::string))
+            }
+        }
+        [other] => Err(type_error("symbol or keyword", other)),
+        _ => Err(arity(name, args.len())),
+    }
+}
+
+fn named(name: &str, args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    let full = match args.as_slice() {
+        [Atom::String(s)] => s.to_string(),
+        [Atom::Symbol(s) | Atom::Keyword(s)] => s.clone(),
+        [Atom::Nil, Atom::String(local)] => local.to_string(),
+        [Atom::String(namespace), Atom::String(local)] => {
+            format!("{}/{}", namespace, local)
+        }
+        [other] | [Atom::Nil | Atom::String(_), other] => return Err(type_error("string", other)),
+        [other, _] => return Err(type_error("string or nil", other)),
+        _ => return Err(arity(name, args.len())),
+    };
+    if name == "keyword" {
+        Ok(Atom::Keyword(full))
+    } else {
+        Ok(Atom::Symbol(full))
+    }
+}
+
+fn resolve_symbol(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::Symbol(sym)] => Ok(resolve_global(sym, env).unwrap_or(Atom::Nil)),
+        [other] => Err(type_error("symbol", other)),
+        _ => Err(arity("resolve", args.len())),
+    }
+}
+
+fn exit(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [] => Err(EvalError::Exit(0)),
+        [Atom::Int(code)] => Err(EvalError::Exit(
+            i32::try_from(*code).map_err(|_| EvalError::Overflow)?,
+        )),
+        [other] => Err(type_error("int", other)),
+        _ => Err(arity("exit", args.len())),
+    }
+}
+
+fn peek(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::Nil] => Ok(Atom::Nil),
+        [Atom::Vector(items)] => Ok(items.last().cloned().unwrap_or(Atom::Nil)),
+        [Atom::List(items)] => Ok(items.first().cloned().unwrap_or(Atom::Nil)),
+        [other] => Err(type_error("list or vector", other)),
+        _ => Err(arity("peek", args.len())),
+    }
+}
+
+fn pop_coll(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match <[Atom; 1]>::try_from(args) {
+        Ok([coll]) => pop(coll),
+        Err(args) => Err(arity("pop", args.len())),
+    }
+}
+
+fn repeat(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::Int(n), x] => Ok(Atom::vector(std::iter::repeat_n(
+            x.clone(),
+            (*n).max(0) as usize,
+        ))),
+        [other, _] => Err(type_error("int", other)),
+        _ => Err(arity("repeat", args.len())),
+    }
+}
+
+fn repeatedly(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::Int(n), f] => (0..(*n).max(0))
+            .map(|_| apply(f, vec![], env))
+            .collect::<Result<Vec<Atom>>>()
+            .map(Atom::vector),
+        [other, _] => Err(type_error("int", other)),
+        _ => Err(arity("repeatedly", args.len())),
+    }
+}
+
+fn iterate(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [f, x, Atom::Int(n)] => {
+            let mut items = Vec::new();
+            let mut x = x.clone();
+            for i in 0..(*n).max(0) {
+                // `x` itself comes first, so `f` is only called n - 1 times
+                if i > 0 {
+                    x = apply(f, vec![x], env)?;
+                }
+                items.push(x.clone());
+            }
+            Ok(Atom::vector(items))
+        }
+        [_, _, other] => Err(type_error("int", other)),
+        _ => Err(arity("iterate", args.len())),
+    }
+}
+
+fn take_or_drop(name: &str, args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::Int(n), coll] => {
+            let items = seq_items(coll)?;
+            // a negative count takes nothing and drops nothing
+            let n = (*n).clamp(0, items.len() as i64) as usize;
+            if name == "take" {
+                Ok(Atom::vector(items.into_iter().take(n)))
+            } else {
+                Ok(Atom::vector(items.into_iter().skip(n)))
+            }
+        }
+        [other, _] => Err(type_error("int", other)),
+        _ => Err(arity(name, args.len())),
+    }
+}
+
+fn take_or_drop_while(name: &str, args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [pred, coll] => {
+            let mut items = seq_items(coll)?;
+            let mut split = items.len();
+            for (i, item) in items.iter().enumerate() {
+                if !apply(pred, vec![item.clone()], env)?.is_truthy() {
+                    split = i;
+                    break;
+                }
+            }
+            let rest = items.split_off(split);
+            Ok(Atom::vector(if name == "take-while" {
+                items
+            } else {
+                rest
+            }))
+        }
+        _ => Err(arity(name, args.len())),
+    }
+}
+
+fn some(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [pred, coll] => {
+            for item in seq_items(coll)? {
+                let result = apply(pred, vec![item], env)?;
+                if result.is_truthy() {
+                    return Ok(result);
+                }
+            }
+            Ok(Atom::Nil)
+        }
+        _ => Err(arity("some", args.len())),
+    }
+}
+
+fn every(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [pred, coll] => {
+            for item in seq_items(coll)? {
+                if !apply(pred, vec![item], env)?.is_truthy() {
+                    return Ok(Atom::Boolean(false));
+                }
+            }
+            Ok(Atom::Boolean(true))
+        }
+        _ => Err(arity("every?", args.len())),
+    }
+}
+
+fn concat(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    let mut items = vec![];
+    for coll in &args {
+        items.extend(seq_items(coll)?);
+    }
+    Ok(Atom::vector(items))
+}
+
+fn mapcat(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [f, coll] => {
+            let mut items = vec![];
+            for item in seq_items(coll)? {
+                items.extend(seq_items(&apply(f, vec![item], env)?)?);
+            }
+            Ok(Atom::vector(items))
+        }
+        _ => Err(arity("mapcat", args.len())),
+    }
+}
+
+fn flatten_nested(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::List(items) | Atom::Vector(items)] => {
+            let mut flat = vec![];
+            flatten(items, &mut flat);
+            Ok(Atom::vector(flat))
+        }
+        // like clojure, anything that isn't sequential has nothing in it
+        [_] => Ok(Atom::vector(vec![])),
+        _ => Err(arity("flatten", args.len())),
+    }
+}
+
+fn distinct(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [coll] => {
+            let mut seen = HashSet::new();
+            Ok(Atom::vector(
+                seq_items(coll)?
+                    .into_iter()
+                    .filter(|item| seen.insert(item.clone())),
+            ))
+        }
+        _ => Err(arity("distinct", args.len())),
+    }
+}
+
+fn update(name: &str, args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.split_first() {
+        Some((m, [path, f, extra @ ..])) => {
+            let path = if name == "update" {
+                vec![path.clone()]
+            } else {
+                seq_items(path)?
+            };
+            if path.is_empty() {
+                return Err(malformed(name, "expected a non-empty path"));
+            }
+            update_in(m.clone(), &path, &mut |old| {
+                let mut args = vec![old];
+                args.extend(extra.iter().cloned());
+                apply(f, args, env)
+            })
+        }
+        _ => Err(arity(name, args.len())),
+    }
+}
+
+fn get_in(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [coll, path] | [coll, path, _] => {
+            let found = seq_items(path)?
+                .iter()
+                .try_fold(coll.clone(), |coll, key| get(&coll, key));
+            Ok(match (found, args.get(2)) {
+                (Some(value), _) => value,
+                (None, Some(default)) => default.clone(),
+                (None, None) => Atom::Nil,
+            })
+        }
+        _ => Err(arity("get-in", args.len())),
+    }
+}
+
+fn assoc_in(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [m, path, value] => {
+            let path = seq_items(path)?;
+            if path.is_empty() {
+                return Err(malformed("assoc-in", "expected a non-empty path"));
+            }
+            update_in(m.clone(), &path, &mut |_| Ok(value.clone()))
+        }
+        _ => Err(arity("assoc-in", args.len())),
+    }
+}
+
+fn zipmap(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [keys, vals] => Ok(Atom::map(
+            seq_items(keys)?.into_iter().zip(seq_items(vals)?),
+        )),
+        _ => Err(arity("zipmap", args.len())),
+    }
+}
+
+fn interleave(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    let colls = args.iter().map(seq_items).collect::<Result<Vec<_>>>()?;
+    // stops with the shortest collection, like zipmap
+    let len = colls.iter().map(Vec::len).min().unwrap_or(0);
+    Ok(Atom::vector((0..len).flat_map(|i| {
+        colls.iter().map(move |coll| coll[i].clone())
+    })))
+}
+
+fn sort_coll(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [coll] => {
+            let items = seq_items(coll)?;
+            sort(items.clone(), items).map(Atom::vector)
+        }
+        _ => Err(arity("sort", args.len())),
+    }
+}
+
+fn sort_by(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [keyfn, coll] => {
+            let items = seq_items(coll)?;
+            let keys = items
+                .iter()
+                .map(|item| apply(keyfn, vec![item.clone()], env))
+                .collect::<Result<Vec<Atom>>>()?;
+            sort(keys, items).map(Atom::vector)
+        }
+        _ => Err(arity("sort-by", args.len())),
+    }
+}
+
+fn reverse(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::String(s)] => Ok(Atom::string(s.chars().rev().collect::<String>())),
+        [Atom::List(items)] => Ok(Atom::list(items.iter().rev().cloned())),
+        [coll] => Ok(Atom::vector(seq_items(coll)?.into_iter().rev())),
+        _ => Err(arity("reverse", args.len())),
+    }
+}
+
+fn vec(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [coll] => Ok(Atom::vector(seq_items(coll)?)),
+        _ => Err(arity("vec", args.len())),
+    }
+}
+
+fn set(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [coll] => Ok(Atom::set(seq_items(coll)?)),
+        _ => Err(arity("set", args.len())),
+    }
+}
+
+fn frequencies(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [coll] => {
+            let mut counts = OrderedMap::new();
+            for item in seq_items(coll)? {
+                match counts.get_mut(&item) {
+                    Some(Atom::Int(n)) => *n += 1,
+                    _ => {
+                        counts.insert(item, Atom::Int(1));
+                    }
+                }
+            }
+            Ok(Atom::Map(Rc::new(counts)))
+        }
+        _ => Err(arity("frequencies", args.len())),
+    }
+}
+
+fn group_by(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [f, coll] => {
+            let mut groups = OrderedMap::new();
+            for item in seq_items(coll)? {
+                let key = apply(f, vec![item.clone()], env)?;
+                match groups.get_mut(&key) {
+                    Some(Atom::Vector(items)) => Rc::make_mut(items).push(item),
+                    _ => {
+                        groups.insert(key, Atom::vector(vec![item]));
+                    }
+                }
+            }
+            Ok(Atom::Map(Rc::new(groups)))
+        }
+        _ => Err(arity("group-by", args.len())),
+    }
+}
+
+fn into(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [to, from] => conj(to.clone(), seq_items(from)?),
+        _ => Err(arity("into", args.len())),
+    }
+}
+
+fn disj(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.split_first() {
+        Some((Atom::Set(set), items)) => {
+            let mut set = set.clone();
+            for item in items {
+                Rc::make_mut(&mut set).remove(item);
+            }
+            Ok(Atom::Set(set))
+        }
+        Some((other, _)) => Err(type_error("set", other)),
+        None => Err(arity("disj", 0)),
+    }
+}
+
+fn keys_or_vals(name: &str, args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::Nil] => Ok(Atom::Nil),
+        // both walk the map in its own iteration order, so the nth key
+        // always belongs with the nth value
+        [Atom::Map(map)] if name == "keys" => Ok(Atom::vector(map.keys().cloned())),
+        [Atom::Map(map)] => Ok(Atom::vector(map.values().cloned())),
+        [other] => Err(type_error("map", other)),
+        _ => Err(arity(name, args.len())),
+    }
+}
+
+fn merge(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    let mut result: Option<OrderedMap> = None;
+    for arg in args {
+        match arg {
+            Atom::Nil => (),
+            Atom::Map(map) => result
+                .get_or_insert_with(OrderedMap::new)
+                .extend(map.iter().map(|(k, v)| (k.clone(), v.clone()))),
+            other => return Err(type_error("map", &other)),
+        }
+    }
+    Ok(result.map_or(Atom::Nil, |map| Atom::Map(Rc::new(map))))
+}
+
+fn format_template(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.split_first() {
+        Some((Atom::String(template), args)) => format(template, args).map(Atom::string),
+        Some((other, _)) => Err(type_error("string", other)),
+        None => Err(arity("format", 0)),
+    }
+}
+
+fn doc(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [f] => {
+            let (name, doc) = match f {
+                Atom::Builtin(name) => (name.to_string(), builtin_doc(name)),
+                Atom::Native(_) => (f.to_string(), None),
+                Atom::Lambda(lambda) => (
+                    lambda.name.clone().unwrap_or_else(|| "fn".to_string()),
+                    lambda.doc.as_deref(),
+                ),
+                other => return Err(type_error("function", other)),
+            };
+            let doc = doc.unwrap_or("No documentation available.");
+            writeln!(env.out(), "-------------------------\n{}\n  {}", name, doc)
+                .map_err(|err| EvalError::Io(err.to_string()))?;
+            Ok(Atom::Nil)
+        }
+        _ => Err(arity("doc", args.len())),
+    }
+}
+
+fn macroexpand_form_1(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [form] => Ok(macroexpand_1(form, env)?.unwrap_or_else(|| form.clone())),
+        _ => Err(arity("macroexpand-1", args.len())),
+    }
+}
+
+fn macroexpand_form(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [form] => macroexpand(form, env),
+        _ => Err(arity("macroexpand", args.len())),
+    }
+}
+
+fn save_bindings(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::String(path)] => save_env(path, env),
+        [other] => Err(type_error("string", other)),
+        _ => Err(arity("save-env", args.len())),
+    }
+}
+
+fn juxt(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    if args.is_empty() {
+        return Err(arity("juxt", 0));
+    }
+    Ok(Atom::Native(Rc::new(Native::Juxt(args))))
+}
+
+fn partial(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.split_first() {
+        Some((f, captured)) => Ok(Atom::Native(Rc::new(Native::Partial(
+            f.clone(),
+            captured.to_vec(),
+        )))),
+        None => Err(arity("partial", 0)),
+    }
+}
+
+fn identity(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [value] => Ok(value.clone()),
+        _ => Err(arity("identity", args.len())),
+    }
+}
+
+fn constantly(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [value] => Ok(Atom::Native(Rc::new(Native::Constantly(value.clone())))),
+        _ => Err(arity("constantly", args.len())),
+    }
+}
+
+fn memoize(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [f] => Ok(Atom::Native(Rc::new(Native::Memoize(
+            f.clone(),
+            RefCell::new(HashMap::new()),
+        )))),
+        _ => Err(arity("memoize", args.len())),
+    }
+}
+
+fn comp(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [] => Ok(Atom::Builtin("identity")),
+        [f] => Ok(f.clone()),
+        _ => Ok(Atom::Native(Rc::new(Native::Comp(args)))),
+    }
+}
+
+fn apply_spread(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [f, spread @ .., last] => {
+            let mut spread = spread.to_vec();
+            spread.extend(seq_items(last)?);
+            apply(f, spread, env)
+        }
+        _ => Err(arity("apply", args.len())),
+    }
+}
+
+fn count_coll(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [coll] => count(coll).map(|n| Atom::Int(n as i64)),
+        _ => Err(arity("count", args.len())),
+    }
+}
+
+fn is_empty(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [coll] => Ok(Atom::Boolean(count(coll)? == 0)),
+        _ => Err(arity("empty?", args.len())),
+    }
+}
+
+fn seq(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [coll] if count(coll)? == 0 => Ok(Atom::Nil),
+        [coll] => Ok(coll.clone()),
+        _ => Err(arity("seq", args.len())),
+    }
+}
+
+fn atom(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [value] => Ok(Atom::Cell(Rc::new(RefCell::new(value.clone())))),
+        _ => Err(arity("atom", args.len())),
+    }
+}
+
+fn deref(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::Cell(cell)] => Ok(cell.borrow().clone()),
+        [other] => Err(type_error("atom", other)),
+        _ => Err(arity("deref", args.len())),
+    }
+}
+
+fn reset(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::Cell(cell), value] => {
+            cell.replace(value.clone());
+            Ok(value.clone())
+        }
+        [other, _] => Err(type_error("atom", other)),
+        _ => Err(arity("reset!", args.len())),
+    }
+}
+
+fn swap(args: Vec<Atom>, env: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::Cell(cell), f, rest @ ..] => {
+            // the borrow ends before `f` runs, so `f` can deref the atom
+            let mut f_args = vec![cell.borrow().clone()];
+            f_args.extend_from_slice(rest);
+            let value = apply(f, f_args, env)?;
+            cell.replace(value.clone());
+            Ok(value)
+        }
+        [other, _, ..] => Err(type_error("atom", other)),
+        _ => Err(arity("swap!", args.len())),
+    }
+}
+
+fn re_pattern(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::String(source)] => Regex::new(source)
+            .map(|regex| Atom::Regex(Rc::new(regex)))
+            .map_err(|err| malformed("re-pattern", &err.to_string())),
+        [regex @ Atom::Regex(_)] => Ok(regex.clone()),
+        [other] => Err(type_error("string", other)),
+        _ => Err(arity("re-pattern", args.len())),
+    }
+}
+
+fn byte_count(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::String(s)] => Ok(Atom::Int(s.len() as i64)),
+        [other] => Err(type_error("string", other)),
+        _ => Err(arity("byte-count", args.len())),
+    }
+}
+
+fn subs(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [_] | [_, _, _, _, ..] | [] => Err(arity("subs", args.len())),
+        [Atom::String(s), Atom::Int(start), rest @ ..] => {
+            let count = s.chars().count();
+            let end = match rest {
+                [Atom::Int(end)] => *end,
+                [other] => return Err(type_error("int", other)),
+                _ => count as i64,
+            };
+            for &index in &[*start, end] {
+                if index < 0 || index > count as i64 {
+                    return Err(EvalError::IndexOutOfBounds { index, count });
+                }
+            }
+            if *start > end {
+                let message = format!("start {} is after end {}", start, end);
+                return Err(malformed("subs", &message));
+            }
+            // the indices count characters, not bytes
+            let sub: String = s
+                .chars()
+                .skip(*start as usize)
+                .take((end - start) as usize)
+                .collect();
+            Ok(Atom::string(sub))
+        }
+        [Atom::String(_), other, ..] => Err(type_error("int", other)),
+        [other, ..] => Err(type_error("string", other)),
+    }
+}
+
+fn str_split(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::String(s), Atom::String(separator)] if separator.is_empty() => {
+            Ok(Atom::vector(s.chars().map(|c| Atom::string(c.to_string()))))
+        }
+        [Atom::String(s), Atom::String(separator)] => {
+            let mut parts: Vec<&str> = s.split(&**separator).collect();
+            // like clojure, trailing empty strings are dropped, but a
+            // string without the separator comes back whole, even ""
+            if s.contains(&**separator) {
+                while parts.last() == Some(&"") {
+                    parts.pop();
+                }
+            }
+            Ok(Atom::vector(parts.into_iter().map(Atom::string)))
+        }
+        [Atom::String(_), other] | [other, _] => Err(type_error("string", other)),
+        _ => Err(arity("str/split", args.len())),
+    }
+}
+
+fn str_join(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    let (separator, coll) = match args.as_slice() {
+        [coll] => ("", coll),
+        [Atom::String(separator), coll] => (&**separator, coll),
+        [other, _] => return Err(type_error("string", other)),
+        _ => return Err(arity("str/join", args.len())),
+    };
+    let parts: Vec<String> = seq_items(coll)?
+        .iter()
+        .map(|item| match item {
+            Atom::Nil => String::new(),
+            item => item.to_string(),
+        })
+        .collect();
+    Ok(Atom::string(parts.join(separator)))
+}
+
+fn re_match(name: &str, args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [Atom::Regex(regex), Atom::String(text)] if name == "re-matches" => {
+            Ok(captures(regex.matches(text)))
+        }
+        [Atom::Regex(regex), Atom::String(text)] => Ok(captures(regex.find(text))),
+        [Atom::Regex(_), other] => Err(type_error("string", other)),
+        [other, _] => Err(type_error("regex", other)),
+        _ => Err(arity(name, args.len())),
+    }
+}
+
+fn contains_key(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [coll, key] => contains(coll, key).map(Atom::Boolean),
+        _ => Err(arity("contains?", args.len())),
+    }
+}
+
+/// Saves the top level bindings to `path`, warning about each one that
+/// can't be saved.
+#[cfg(feature = "persist")]
+fn save_env(path: &str, env: &mut ENV) -> Result<Atom> {
+    let skipped = env
+        .save(path)
+        .map_err(|err| EvalError::Io(format!("save-env: {}", err)))?;
+    for (name, what) in skipped {
+        writeln!(
+            env.out(),
+            "WARNING: not saving {}, it holds a {}, which can't be saved",
+            name,
+            what
+        )
+        .map_err(|err| EvalError::Io(err.to_string()))?;
+    }
+    Ok(Atom::Nil)
+}
+
+#[cfg(not(feature = "persist"))]
+fn save_env(_path: &str, _env: &mut ENV) -> Result<Atom> {
+    Err(EvalError::Malformed(
+        "save-env: warp was built without the persist feature".to_string(),
+    ))
+}
+
+/// printf style formatting supporting `%d`, `%s`, `%f` (with an optional
+/// precision, as in `%.2f`) and `%%`. Every argument has to be used.
+fn format(template: &str, args: &[Atom]) -> Result<String> {
+    let mut result = String::new();
+    let mut args = args.iter();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+        let mut precision: Option<usize> = None;
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            let mut digits = String::new();
+            while let Some(digit) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                digits.push(*digit);
+                chars.next();
+            }
+            precision = digits.parse().ok();
+        }
+        let directive = chars.next();
+        if directive == Some('%') {
+            result.push('%');
+            continue;
+        }
+        let arg = args
+            .next()
+            .ok_or_else(|| malformed("format", "not enough arguments for the format string"))?;
+        match (directive, arg) {
+            (Some('d'), Atom::Int(n)) => result.push_str(&n.to_string()),
+            (Some('d'), other) => return Err(type_error("int", other)),
+            (Some('f'), arg) => {
+                let n = number(arg)?;
+                result.push_str(&format!("{:.*}", precision.unwrap_or(6), n));
+            }
+            (Some('s'), arg) => result.push_str(&arg.to_string()),
+            (Some(other), _) => {
+                return Err(malformed(
+                    "format",
+                    &format!("unknown directive %{}", other),
+                ))
+            }
+            (None, _) => return Err(malformed("format", "format string ends with %")),
+        }
+    }
+    if args.next().is_some() {
+        return Err(malformed(
+            "format",
+            "too many arguments for the format string",
+        ));
+    }
+    Ok(result)
+}
+
+/// Stably sorts `items` by comparing the matching `keys` with `order`,
+/// failing if any two can't be compared. Only values of the same kind
+/// compare, so checking every key against the first one up front means the
+/// sort itself never meets a pair it can't order.
+fn sort(keys: Vec<Atom>, items: Vec<Atom>) -> Result<Vec<Atom>> {
+    if let Some(first) = keys.first() {
+        for key in &keys {
+            order(first, key)?;
+        }
+    }
+    let mut keyed: Vec<(Atom, Atom)> = keys.into_iter().zip(items).collect();
+    keyed.sort_by(|(a, _), (b, _)| order(a, b).expect("sort keys were checked up front"));
+    Ok(keyed.into_iter().map(|(_, item)| item).collect())
+}
+
+/// The ordering `sort` uses: numbers by value, strings and keywords
+/// lexicographically. Anything else, or two values of different kinds,
+/// can't be compared.
+fn order(a: &Atom, b: &Atom) -> Result<Ordering> {
+    match (a, b) {
+        (Atom::Int(a), Atom::Int(b)) => Ok(a.cmp(b)),
+        (
+            Atom::Int(_) | Atom::Float(_) | Atom::Ratio(..),
+            Atom::Int(_) | Atom::Float(_) | Atom::Ratio(..),
+        ) => Ok(number(a)?.total_cmp(&number(b)?)),
+        (Atom::String(a), Atom::String(b)) => Ok(a.cmp(b)),
+        (Atom::Keyword(a), Atom::Keyword(b)) => Ok(a.cmp(b)),
+        _ => Err(EvalError::Type {
+            expected: "comparable values",
+            got: format!("{} and {}", a.type_name(), b.type_name()),
+        }),
+    }
+}
+
+/// What `re-find` and `re-matches` return: `nil` for no match, the matched
+/// text for a pattern without groups, and otherwise a vector of the match
+/// followed by each group, `nil` for the groups that didn't take part.
+fn captures(captures: Option<Captures>) -> Atom {
+    let mut captures = match captures {
+        Some(captures) => captures,
+        None => return Atom::Nil,
+    };
+    if captures.len() == 1 {
+        return captures.pop().unwrap().map_or(Atom::Nil, Atom::string);
+    }
+    Atom::vector(
+        captures
+            .into_iter()
+            .map(|group| group.map_or(Atom::Nil, Atom::string)),
+    )
+}
+
+/// Splits `ns/name` at its first `/`. A name without one, or `/` itself,
+/// has no namespace.
+fn split_namespace(s: &str) -> (Option<&str>, &str) {
+    match s.split_once('/') {
+        Some((namespace, name)) if !namespace.is_empty() && !name.is_empty() => {
+            (Some(namespace), name)
+        }
+        _ => (None, s),
+    }
+}
+
+/// The number of elements in a collection, or characters in a string.
+/// `nil` counts as empty.
+fn count(coll: &Atom) -> Result<usize> {
+    match coll {
+        Atom::Nil => Ok(0),
+        Atom::List(items) | Atom::Vector(items) => Ok(items.len()),
+        Atom::Map(map) => Ok(map.len()),
+        Atom::Set(set) => Ok(set.len()),
+        Atom::String(s) => Ok(s.chars().count()),
+        other => Err(type_error("collection or string", other)),
+    }
+}
+
+/// Whether `key` is a key of `coll`: a key for maps, an element for sets,
+/// and, as in clojure, a valid *index* for vectors and strings. So
+/// `(contains? [:a :b] 1)` is true and `(contains? [:a :b] :a)` is false.
+fn contains(coll: &Atom, key: &Atom) -> Result<bool> {
+    let len = match coll {
+        Atom::Nil => return Ok(false),
+        Atom::Map(map) => return Ok(map.contains_key(key)),
+        Atom::Set(set) => return Ok(set.contains(key)),
+        Atom::Vector(items) => items.len(),
+        Atom::String(s) => s.chars().count(),
+        other => return Err(type_error("map, set, vector or string", other)),
+    };
+    match key {
+        Atom::Int(index) => Ok(*index >= 0 && (*index as usize) < len),
+        _ => Ok(false),
+    }
+}
+
+/// Replaces the value at `path` in nested maps with `f` of it, making maps
+/// along the way for keys that aren't there. `nil` counts as an empty map.
+/// `path` can't be empty.
+fn update_in(coll: Atom, path: &[Atom], f: &mut dyn FnMut(Atom) -> Result<Atom>) -> Result<Atom> {
+    let (key, rest) = path.split_first().expect("a non-empty path");
+    let mut map = match coll {
+        Atom::Nil => Rc::new(OrderedMap::new()),
+        Atom::Map(map) => map,
+        other => return Err(type_error("map", &other)),
+    };
+    let old = map.get(key).cloned().unwrap_or(Atom::Nil);
+    let new = if rest.is_empty() {
+        f(old)?
+    } else {
+        update_in(old, rest, f)?
+    };
+    Rc::make_mut(&mut map).insert(key.clone(), new);
+    Ok(Atom::Map(map))
+}
+
+/// `coll` without the element `peek` would return: the last of a vector,
+/// the first of a list. Like `conj`, a vector only `coll` holds is changed in
+/// place.
+fn pop(coll: Atom) -> Result<Atom> {
+    match coll {
+        Atom::Nil => Ok(Atom::Nil),
+        Atom::Vector(items) if items.is_empty() => Err(EvalError::EmptyPop("vector")),
+        Atom::List(items) if items.is_empty() => Err(EvalError::EmptyPop("list")),
+        Atom::Vector(mut items) => {
+            Rc::make_mut(&mut items).pop();
+            Ok(Atom::Vector(items))
+        }
+        Atom::List(items) => Ok(Atom::list(items[1..].iter().cloned())),
+        other => Err(type_error("list or vector", &other)),
+    }
+}
+
+/// Adds `items` to `coll` wherever that collection adds most cheaply: the end
+/// of a vector, the front of a list. Vectors, sets and maps are copied on
+/// write: one that's also held elsewhere, say by a `def`, is copied first,
+/// while one only `coll` holds, like the result of another `conj`, is added
+/// to in place. The copy takes time linear in the size of the collection.
+fn conj(coll: Atom, items: Vec<Atom>) -> Result<Atom> {
+    match coll {
+        Atom::Nil => conj(Atom::list(vec![]), items),
+        Atom::Vector(mut vector) => {
+            Rc::make_mut(&mut vector).extend(items);
+            Ok(Atom::Vector(vector))
+        }
+        Atom::List(list) => {
+            let mut result: Vec<Atom> = items.into_iter().rev().collect();
+            result.extend(list.iter().cloned());
+            Ok(Atom::list(result))
+        }
+        Atom::Set(mut set) => {
+            Rc::make_mut(&mut set).extend(items);
+            Ok(Atom::Set(set))
+        }
+        Atom::Map(mut map) => {
+            for item in items {
+                match item {
+                    Atom::Vector(pair) if pair.len() == 2 => {
+                        Rc::make_mut(&mut map).insert(pair[0].clone(), pair[1].clone());
+                    }
+                    other => return Err(type_error("[key value] pair", &other)),
+                }
+            }
+            Ok(Atom::Map(map))
+        }
+        other => Err(type_error("collection", &other)),
+    }
+}
+
+fn number(atom: &Atom) -> Result<f64> {
+    match atom {
+        Atom::Int(n) => Ok(*n as f64),
+        Atom::Float(n) => Ok(*n),
+        Atom::Ratio(n, d) => Ok(*n as f64 / *d as f64),
+        other => Err(type_error("number", other)),
+    }
+}
+
+/// The arithmetic builtins `Builtins::standard` starts with.
+pub(crate) const ARITHMETIC: &[(&str, BuiltinFn)] = &[
+    ("+", add),
+    ("-", subtract),
+    ("*", multiply),
+    ("/", divide_all),
+    ("=", equal),
+    ("not=", not_equal),
+    ("<", |args, _| compare(&args, |a, b| a < b)),
+    (">", |args, _| compare(&args, |a, b| a > b)),
+    ("<=", |args, _| compare(&args, |a, b| a <= b)),
+    (">=", |args, _| compare(&args, |a, b| a >= b)),
+];
+
+fn add(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    arithmetic(
+        &args,
+        Atom::Int(0),
+        i64::checked_add,
+        add_fractions,
+        |a, b| a + b,
+    )
+}
+
+fn multiply(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    arithmetic(
+        &args,
+        Atom::Int(1),
+        i64::checked_mul,
+        |(a, b), (c, d)| Some((a.checked_mul(c)?, b.checked_mul(d)?)),
+        |a, b| a * b,
+    )
+}
+
+fn subtract(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    let subtract_fractions = |a, (c, d): Fraction| add_fractions(a, (c.checked_neg()?, d));
+    match args.as_slice() {
+        [] => Err(arity("-", 0)),
+        [only] => arithmetic(
+            &[Atom::Int(0), only.clone()],
+            Atom::Int(0),
+            i64::checked_sub,
+            subtract_fractions,
+            |a, b| a - b,
+        ),
+        [first, rest @ ..] => arithmetic(
+            rest,
+            first.clone(),
+            i64::checked_sub,
+            subtract_fractions,
+            |a, b| a - b,
+        ),
+    }
+}
+
+fn add_fractions((a, b): Fraction, (c, d): Fraction) -> Option<Fraction> {
+    let numerator = a.checked_mul(d)?.checked_add(c.checked_mul(b)?)?;
+    Some((numerator, b.checked_mul(d)?))
+}
+
+fn divide_all(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    match args.as_slice() {
+        [] => Err(arity("/", 0)),
+        [only] => divide(&Atom::Int(1), only),
+        [first, rest @ ..] => rest
+            .iter()
+            .try_fold(first.clone(), |acc, arg| divide(&acc, arg)),
+    }
+}
+
+fn equal(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    Ok(Atom::Boolean(
+        args.windows(2).all(|pair| pair[0] == pair[1]),
+    ))
+}
+
+fn not_equal(args: Vec<Atom>, _: &mut ENV) -> Result<Atom> {
+    if args.is_empty() {
+        return Err(arity("not=", 0));
+    }
+    Ok(Atom::Boolean(
+        !args.windows(2).all(|pair| pair[0] == pair[1]),
+    ))
+}
+
+/// An int or ratio as a numerator and denominator, widened so combining
+/// two of them can't overflow before `Atom::ratio` reduces the result.
+type Fraction = (i128, i128);
+
+fn fraction(atom: &Atom) -> Option<Fraction> {
+    match atom {
+        Atom::Int(n) => Some((i128::from(*n), 1)),
+        Atom::Ratio(n, d) => Some((i128::from(*n), i128::from(*d))),
+        _ => None,
+    }
+}
+
+/// Folds `args` into `init`, staying exact while everything is an int or a
+/// ratio and becoming a float once a float shows up.
+fn arithmetic(
+    args: &[Atom],
+    init: Atom,
+    int_op: fn(i64, i64) -> Option<i64>,
+    ratio_op: fn(Fraction, Fraction) -> Option<Fraction>,
+    float_op: fn(f64, f64) -> f64,
+) -> Result<Atom> {
+    args.iter().try_fold(init, |acc, arg| match (&acc, arg) {
+        (Atom::Int(a), Atom::Int(b)) => int_op(*a, *b).map(Atom::Int).ok_or(EvalError::Overflow),
+        _ => match (fraction(&acc), fraction(arg)) {
+            (Some(a), Some(b)) => ratio_op(a, b)
+                .and_then(|(n, d)| Atom::ratio(n, d))
+                .ok_or(EvalError::Overflow),
+            _ => Ok(Atom::Float(float_op(number(&acc)?, number(arg)?))),
+        },
+    })
+}
+
+/// Dividing ints and ratios is exact: an int when it divides evenly and a
+/// ratio otherwise, so `(/ 1 2)` is `1/2`. With a float it's a float.
+fn divide(a: &Atom, b: &Atom) -> Result<Atom> {
+    match (fraction(a), fraction(b)) {
+        (Some(_), Some((0, _))) => Err(EvalError::DivideByZero),
+        (Some((a, b)), Some((c, d))) => Atom::ratio(a * d, b * c).ok_or(EvalError::Overflow),
+        _ => Ok(Atom::Float(number(a)? / number(b)?)),
+    }
+}
+
+fn compare(args: &[Atom], op: fn(f64, f64) -> bool) -> Result<Atom> {
+    let numbers = args.iter().map(number).collect::<Result<Vec<f64>>>()?;
+    Ok(Atom::Boolean(
+        numbers.windows(2).all(|pair| op(pair[0], pair[1])),
+    ))
+}
@@ -0,0 +1,40 @@
+// `Atom::Cell` is mutable inside, but it hashes and compares by pointer, so
+// mutating one never changes where it belongs in a map or set.
+#![allow(clippy::mutable_key_type)]
+
+pub mod ast;
+pub mod atom;
+pub mod builtins;
+pub mod check;
+pub mod env;
+pub mod eval;
+pub mod map;
+#[cfg(feature = "persist")]
+pub mod persist;
+pub mod pretty;
+pub mod regex;
+pub mod repl;
+pub mod run;
+pub mod stats;
+pub mod syntax_quote;
+
+pub use crate::ast::{
+    Dispatch, Form, FormRef, LexError, Node, ParseError, ParseErrorKind, Reader, SExp, TagHandler,
+    Token, AST,
+};
+pub use crate::atom::{Atom, HostClosure, HostFn, Lambda, Native};
+pub use crate::builtins::{BuiltinFn, Builtins};
+pub use crate::check::{Diagnostic, Severity};
+pub use crate::env::{EnvSnapshot, Scope, ENV};
+pub use crate::eval::{
+    apply, eval, eval_defining, eval_form, eval_located, macroexpand, macroexpand_1, EvalError,
+};
+pub use crate::map::OrderedMap;
+#[cfg(feature = "persist")]
+pub use crate::persist::PersistError;
+pub use crate::pretty::PrettyPrinter;
+pub use crate::regex::{Regex, RegexError};
+pub use crate::repl::Repl;
+pub use crate::run::{run_file, RunError};
+pub use crate::stats::Stats;
+pub use crate::syntax_quote::syntax_quote;
@@ -1,296 +1,218 @@
-#![allow(dead_code)]
-use std::collections::{HashMap, VecDeque};
-use std::fmt;
+use std::env;
 use std::fs;
-use std::hash::{Hash, Hasher};
-
-#[derive(Debug)]
-struct Token {
-    value: String,
-}
-
-impl fmt::Display for Token {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.value)
-    }
-}
-
-#[derive(Debug, PartialEq)]
-enum Atom {
-    Symbol(String),
-    Keyword(String),
-    Int(i64),
-    Float(f64),
-    String(String),
-    Boolean(bool),
-    Reference(usize),
-}
-
-impl Atom {
-    fn infer(src: &Token) -> Option<Atom> {
-        let src = src.value.clone();
-        // handling the boolean case
-        match src.as_str() {
-            "true" => {
-                return Some(Atom::Boolean(true));
-            }
-            "false" => {
-                return Some(Atom::Boolean(false));
+use std::io::{self, IsTerminal, Read, Write};
+use std::process;
+
+use warp::{eval_located, EvalError, Repl, AST, ENV};
+
+/// Command line options:
+/// `warp [--no-banner] [--no-asserts] [--trace] [--color] [--print-tokens] [--print-ast]
+/// [--load-env file] [--diagnostics=text|json] [path]`. A path of `-` reads the program from stdin.
+struct Options {
+    path: Option<String>,
+    banner: bool,
+    asserts: bool,
+    /// Write every function call and its result to stdout.
+    trace: bool,
+    /// Color the REPL's results, if stdout is a terminal.
+    color: bool,
+    /// Just tokenize the program and print the tokens.
+    print_tokens: bool,
+    /// Just read the program and print what was read.
+    print_ast: bool,
+    load_env: Option<String>,
+    /// Report errors in a program as JSON lines rather than text.
+    json_diagnostics: bool,
+}
+
+impl Options {
+    fn parse() -> Options {
+        let mut options = Options {
+            path: None,
+            banner: true,
+            asserts: true,
+            trace: false,
+            color: false,
+            print_tokens: false,
+            print_ast: false,
+            load_env: None,
+            json_diagnostics: false,
+        };
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--no-banner" => options.banner = false,
+                "--no-asserts" => options.asserts = false,
+                "--trace" => options.trace = true,
+                "--color" => options.color = true,
+                "--print-tokens" => options.print_tokens = true,
+                "--print-ast" => options.print_ast = true,
+                "--diagnostics=text" => options.json_diagnostics = false,
+                "--diagnostics=json" => options.json_diagnostics = true,
+                "--load-env" => match args.next() {
+                    Some(file) => options.load_env = Some(file),
+                    None => {
+                        eprintln!("--load-env needs a file");
+                        process::exit(2);
+                    }
+                },
+                flag if flag.starts_with("--") => {
+                    eprintln!("unknown option: {}", flag);
+                    process::exit(2);
+                }
+                _ => options.path = Some(arg),
             }
-            _ => (),
         }
-
-        // int
-        if let Ok(value) = src.parse::<i64>() {
-            return Some(Atom::Int(value));
-        }
-
-        // float
-        if let Ok(value) = src.parse::<f64>() {
-            return Some(Atom::Float(value));
-        }
-
-        Some(Atom::Symbol(src))
+        options
     }
 }
 
-impl PartialEq for Atom {
-    fn eq(&self, other: &Atom) -> bool {}
-}
-
-impl Hash for Atom {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        match self {
-            Atom::Boolean(f) => f.to_string().hash(state),
-            Atom::Float(f) => f.to_string().hash(state),
-            Atom::Int(f) => f.to_string().hash(state),
-            Atom::Keyword(f) => f.to_string().hash(state),
-            Atom::Reference(f) => f.to_string().hash(state),
-            Atom::String(f) => f.to_string().hash(state),
-            Atom::Symbol(f) => f.to_string().hash(state),
-        }
+#[cfg(feature = "persist")]
+fn load_env(env: &mut ENV, path: &str) {
+    if let Err(err) = env.load(path) {
+        eprintln!("could not load {}: {}", path, err);
+        process::exit(1);
     }
 }
 
-impl fmt::Display for Atom {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Atom::Boolean(v) => write!(f, "{}", v),
-            Atom::Float(v) => write!(f, "{}", v),
-            Atom::Int(v) => write!(f, "{}", v),
-            Atom::Keyword(v) => write!(f, "{}", v),
-            Atom::Reference(v) => write!(f, "%{}", v),
-            Atom::String(v) => write!(f, "{}", v),
-            Atom::Symbol(v) => write!(f, "{}", v),
-        }
+#[cfg(not(feature = "persist"))]
+fn load_env(_env: &mut ENV, _path: &str) {
+    eprintln!("--load-env needs warp to be built with the persist feature");
+    process::exit(2);
+}
+
+/// Exits with `code` once everything printed so far is written out, since
+/// `process::exit` doesn't flush stdout.
+fn exit(code: i32) -> ! {
+    let _ = io::stdout().flush();
+    process::exit(code)
+}
+
+/// Prints an error in the program to stderr, as `kind: line:col: message` or,
+/// with `--diagnostics=json`, as a line like
+/// `{"severity":"error","line":3,"col":5,"message":"..."}`. An evaluation
+/// error's position is where the top level form it came from starts.
+fn report(options: &Options, kind: &str, position: Option<(usize, usize)>, message: &str) {
+    match (options.json_diagnostics, position) {
+        (true, Some((line, col))) => eprintln!(
+            r#"{{"severity":"error","line":{},"col":{},"message":{}}}"#,
+            line,
+            col,
+            json_string(message)
+        ),
+        (true, None) => eprintln!(
+            r#"{{"severity":"error","line":null,"col":null,"message":{}}}"#,
+            json_string(message)
+        ),
+        (false, Some((line, col))) => eprintln!("{}: {}:{}: {}", kind, line, col, message),
+        (false, None) => eprintln!("{}: {}", kind, message),
     }
 }
 
-#[derive(Debug)]
-struct SExp {
-    _type: &'static str,
-    children: Vec<Atom>,
-}
-
-impl SExp {
-    fn new(t: &Token) -> SExp {
-        let _type = match t.value.as_str() {
-            "(" => "exec",
-            "[" => "vec",
-            "{" => "map",
-            "\"" => "string",
-            "'" => "list",
-            _ => panic!("Unsupported type"),
-        };
-
-        SExp {
-            _type,
-            children: vec![],
+/// `s` as a quoted JSON string.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
         }
     }
-
-    fn push(&mut self, atom: Atom) {
-        self.children.push(atom);
-    }
+    quoted.push('"');
+    quoted
 }
 
-impl fmt::Display for SExp {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let output = format!("({}", self._type);
-        let mut body: Vec<String> = vec![];
-        for child in &self.children {
-            body.push(child.to_string());
-        }
-        let body = body.join(" ");
-        let output = format!("{} {})", output, body);
-
-        write!(f, "{}", output)
+fn main() {
+    let options = Options::parse();
+    let mut env = ENV::new();
+    env.assertions = options.asserts;
+    env.trace = options.trace;
+    if let Some(path) = &options.load_env {
+        load_env(&mut env, path);
     }
-}
-
-#[derive(Debug)]
-struct AST {
-    items: HashMap<usize, SExp>,
-}
-
-impl AST {
-    fn tokenize(src: String) -> Vec<Token> {
-        let mut strings: Vec<String> = vec![];
-
-        for c in src.chars() {
-            if let Some(token) = strings.last() {
-                match token.as_str() {
-                    "{" | "}" | "[" | "]" | "(" | ")" | " " | "\n" | "\"" | "'" | "@" | "~"
-                    | "`" => strings.push(c.to_string()),
-                    _ => match c {
-                        '}' | '{' | ']' | '[' | ' ' | ')' | '(' | '\n' | '"' | '\'' | '@' | '~'
-                        | '`' => strings.push(c.to_string()),
-                        _ => {
-                            let mut token = strings.pop().unwrap();
-                            token += c.to_string().as_str();
-                            strings.push(token);
-                        }
-                    },
-                }
-            } else {
-                strings.push(c.to_string());
+    // a program piped in is run like a file, but its result is printed
+    let from_stdin = match options.path.as_deref() {
+        Some("-") => true,
+        Some(_) => false,
+        None => !io::stdin().is_terminal(),
+    };
+    let contents = match &options.path {
+        _ if from_stdin => {
+            let mut contents = String::new();
+            if let Err(err) = io::stdin().read_to_string(&mut contents) {
+                eprintln!("could not read stdin: {}", err);
+                process::exit(1);
             }
+            contents
         }
-
-        let mut tokens: Vec<Token> = vec![];
-
-        for s in strings {
-            match s.as_str() {
-                " " | "\n" | "\t" => (),
-                _ => {
-                    tokens.push(Token { value: s });
-                }
+        Some(path) => match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                eprintln!("could not read {}: {}", path, err);
+                process::exit(1);
             }
-        }
-
-        tokens
-    }
-
-    fn read(tokens: &Vec<Token>) -> AST {
-        let mut items: HashMap<usize, SExp> = HashMap::new();
-        let mut sexps: Vec<SExp> = vec![];
-        let mut id: usize = 0;
-        let mut ids: VecDeque<usize> = VecDeque::new();
-
-        for token in tokens {
-            match token.value.as_str() {
-                "\"" => {}
-                "'" => {}
-                "(" | "[" | "{" => {
-                    if let Some(sexp) = sexps.last_mut() {
-                        sexp.push(Atom::Reference(id));
-                    }
-                    ids.push_back(id);
-                    id += 1;
-                    sexps.push(SExp::new(token));
-                }
-                ")" | "]" | "}" => {
-                    items.insert(
-                        ids.pop_back().expect("No more items left"),
-                        sexps.pop().unwrap(),
-                    );
-                }
-                _ => {
-                    let atom = Atom::infer(token).unwrap();
-                    if let Some(sexp) = sexps.last_mut() {
-                        sexp.push(atom);
-                    }
+        },
+        None => {
+            let mut repl = Repl::with_env(env);
+            if !options.banner {
+                repl.banner = None;
+            }
+            repl.color = options.color && io::stdout().is_terminal();
+            let stdin = io::stdin();
+            match repl.run(&mut stdin.lock(), &mut io::stdout()) {
+                Ok(code) => exit(code),
+                Err(err) => {
+                    eprintln!("error: {}", err);
+                    process::exit(1);
                 }
             }
         }
+    };
 
-        AST { items }
-    }
-}
-
-struct ENV {
-    vars: HashMap<Atom, Atom>,
-}
-
-impl ENV {
-    fn new() -> ENV {
-        ENV {
-            vars: HashMap::new(),
+    // NOTE: These two lines should probably be one. (I think).
+    let tokens = match AST::tokenize(contents) {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            report(&options, "lex error", Some(err.position()), &err.message());
+            process::exit(1);
         }
-    }
-}
-
-fn eval(ast: &AST, env: &mut ENV, pc: usize) -> usize {
-    let mut max_pc: usize = pc;
-    if let Some(sexp) = ast.items.get(&pc) {
-        println!("{}", sexp);
-        for atom in &sexp.children {
-            match atom {
-                Atom::Reference(n) => {
-                    if n > &max_pc {
-                        max_pc = *n;
-                    }
-                    let local_max = eval(&ast, &mut env, n.clone());
-                    if local_max > max_pc {
-                        max_pc = local_max;
-                    }
-                }
-                _atom => {}
-            }
+    };
+    if options.print_tokens {
+        for token in &tokens {
+            println!("{}:{}\t{}", token.line, token.col, token);
         }
+        return;
     }
-    max_pc
-}
-
-// fn eval(ast: &AST, pc: usize) {
-//     let reference = pc;
-//     let mut pc: usize = pc;
-//     while ast.items.len() > pc {
-//         if let Some(sexp) = ast.items.get(&pc) {
-//             println!("{} {}", pc, sexp);
-//             for atom in &sexp.children {
-//                 match atom {
-//                     Atom::Reference(n) => {
-//                         if n > &pc {
-//                             pc = n + 1;
-//                         }
-//                         eval(&ast, n.clone());
-//                     }
-//                     _atom => {}
-//                 }
-//             }
-//             if pc == reference {
-//                 return;
-//             }
-//             pc += 1;
-//         }
-//     }
-// }
-
-fn main() {
-    let contents = fs::read_to_string("./src.clj").expect("Could not read file.");
-
-    // NOTE: These two lines should probably be one. (I think).
-    let tokens = AST::tokenize(contents);
-    let ast = AST::read(&tokens);
-
-    // println!("{:#?}", ast);
-    // println!("{:#?}", tokens);
-
-    // for (id, sexp) in &ast.items {
-    //     println!("{} {}", id, sexp);
-    // }
+    let ast = match AST::read(&tokens) {
+        Ok(ast) => ast,
+        Err(err) => {
+            report(
+                &options,
+                "parse error",
+                Some((err.line, err.col)),
+                &err.message(),
+            );
+            process::exit(1);
+        }
+    };
 
-    let mut pc: usize = 0;
-    let mut env = ENV::new();
-    while pc < ast.items.len() {
-        pc = eval(&ast, &mut env, pc) + 1;
-        println!("");
+    if options.print_ast {
+        print!("{}", ast);
+        return;
     }
-    return;
 
-    let max = ast.items.len();
-    for i in 0..max {
-        println!("{}\t{}", i, ast.items[&i]);
+    match eval_located(&ast, &mut env) {
+        Ok(result) if from_stdin => println!("{}", result.readable()),
+        Ok(_) => (),
+        Err(EvalError::Exit(code)) => exit(code),
+        Err(err) => {
+            report(&options, "error", err.position(), &err.message());
+            process::exit(1);
+        }
     }
 }
@@ -0,0 +1,112 @@
+//! The map behind `Atom::Map`: a hash map that remembers the order its keys
+//! were first inserted in, so `keys`, `vals` and printing are deterministic.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::FromIterator;
+
+use crate::atom::Atom;
+
+#[derive(Clone, Default)]
+pub struct OrderedMap {
+    /// The entries in insertion order.
+    entries: Vec<(Atom, Atom)>,
+    /// Where each key's entry is in `entries`.
+    index: HashMap<Atom, usize>,
+}
+
+impl OrderedMap {
+    pub fn new() -> OrderedMap {
+        OrderedMap::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &Atom) -> Option<&Atom> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &Atom) -> Option<&mut Atom> {
+        let i = *self.index.get(key)?;
+        Some(&mut self.entries[i].1)
+    }
+
+    pub fn contains_key(&self, key: &Atom) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Sets `key` to `value`, returning the old value. A key that's already
+    /// there keeps its place; a new one goes at the end.
+    pub fn insert(&mut self, key: Atom, value: Atom) -> Option<Atom> {
+        if let Some(&i) = self.index.get(&key) {
+            return Some(std::mem::replace(&mut self.entries[i].1, value));
+        }
+        self.index.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+        None
+    }
+
+    /// Removes `key`, keeping the rest of the entries in order. That shifts
+    /// every later entry down, so it's linear in the size of the map.
+    pub fn remove(&mut self, key: &Atom) -> Option<Atom> {
+        let i = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        for (key, _) in &self.entries[i..] {
+            *self.index.get_mut(key).unwrap() -= 1;
+        }
+        Some(value)
+    }
+
+    /// The entries in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Atom, &Atom)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Atom> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Atom> {
+        self.entries.iter().map(|(_, value)| value)
+    }
+}
+
+/// Two maps are equal if they have the same entries, in any order.
+impl PartialEq for OrderedMap {
+    fn eq(&self, other: &OrderedMap) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl Eq for OrderedMap {}
+
+impl fmt::Debug for OrderedMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<(Atom, Atom)> for OrderedMap {
+    fn from_iter<I: IntoIterator<Item = (Atom, Atom)>>(entries: I) -> OrderedMap {
+        let mut map = OrderedMap::new();
+        map.extend(entries);
+        map
+    }
+}
+
+impl Extend<(Atom, Atom)> for OrderedMap {
+    fn extend<I: IntoIterator<Item = (Atom, Atom)>>(&mut self, entries: I) {
+        for (key, value) in entries {
+            self.insert(key, value);
+        }
+    }
+}
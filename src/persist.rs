@@ -0,0 +1,178 @@
+//! Saving the top level bindings of an `ENV` to a file and loading them back.
+//! The file is JSON written with serde: an object from each name to its value
+//! as a `Value`, which mirrors the atoms that are plain data. Functions,
+//! macros and `atom`s aren't data, so bindings holding them aren't saved.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::atom::Atom;
+use crate::env::ENV;
+use crate::regex::{Regex, RegexError};
+
+#[derive(Debug)]
+pub enum PersistError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// A saved regex that no longer compiles.
+    Regex(RegexError),
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PersistError::Io(err) => write!(f, "{}", err),
+            PersistError::Json(err) => write!(f, "{}", err),
+            PersistError::Regex(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for PersistError {
+    fn from(err: io::Error) -> PersistError {
+        PersistError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for PersistError {
+    fn from(err: serde_json::Error) -> PersistError {
+        PersistError::Json(err)
+    }
+}
+
+/// The saved form of an atom.
+#[derive(Debug, Serialize, Deserialize)]
+enum Value {
+    Nil,
+    Symbol(String),
+    Keyword(String),
+    Int(i64),
+    Float(f64),
+    Ratio(i64, i64),
+    String(String),
+    Char(char),
+    Boolean(bool),
+    List(Vec<Value>),
+    Vector(Vec<Value>),
+    /// Entries in the map's order.
+    Map(Vec<(Value, Value)>),
+    Set(Vec<Value>),
+    /// The pattern's source.
+    Regex(String),
+}
+
+impl Value {
+    /// `atom` as a `Value`, or a description of the first thing in it that
+    /// can't be saved.
+    fn from_atom(atom: &Atom) -> Result<Value, &'static str> {
+        let all = |items: &mut dyn Iterator<Item = &Atom>| {
+            items.map(Value::from_atom).collect::<Result<Vec<_>, _>>()
+        };
+        let value = match atom {
+            Atom::Nil => Value::Nil,
+            Atom::Symbol(name) => Value::Symbol(name.clone()),
+            Atom::Keyword(name) => Value::Keyword(name.clone()),
+            Atom::Int(n) => Value::Int(*n),
+            // JSON has no infinity or NaN
+            Atom::Float(n) if !n.is_finite() => return Err("non-finite float"),
+            Atom::Float(n) => Value::Float(*n),
+            Atom::Ratio(n, d) => Value::Ratio(*n, *d),
+            Atom::String(s) => Value::String(s.to_string()),
+            Atom::Char(c) => Value::Char(*c),
+            Atom::Boolean(b) => Value::Boolean(*b),
+            Atom::List(items) => Value::List(all(&mut items.iter())?),
+            Atom::Vector(items) => Value::Vector(all(&mut items.iter())?),
+            Atom::Set(items) => Value::Set(all(&mut items.iter())?),
+            Atom::Map(map) => Value::Map(
+                map.iter()
+                    .map(|(key, value)| Ok((Value::from_atom(key)?, Value::from_atom(value)?)))
+                    .collect::<Result<Vec<_>, &'static str>>()?,
+            ),
+            Atom::Regex(regex) => Value::Regex(regex.as_str().to_string()),
+            Atom::Lambda(_) | Atom::Builtin(_) | Atom::Native(_) => return Err("function"),
+            Atom::Macro(_) => return Err("macro"),
+            Atom::Cell(_) => return Err("atom"),
+            Atom::Reference(_) => return Err("reference"),
+        };
+        Ok(value)
+    }
+
+    fn into_atom(self) -> Result<Atom, PersistError> {
+        let all = |items: Vec<Value>| {
+            items
+                .into_iter()
+                .map(Value::into_atom)
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let atom = match self {
+            Value::Nil => Atom::Nil,
+            Value::Symbol(name) => Atom::Symbol(name),
+            Value::Keyword(name) => Atom::Keyword(name),
+            Value::Int(n) => Atom::Int(n),
+            Value::Float(n) => Atom::Float(n),
+            Value::Ratio(n, d) => Atom::Ratio(n, d),
+            Value::String(s) => Atom::string(s),
+            Value::Char(c) => Atom::Char(c),
+            Value::Boolean(b) => Atom::Boolean(b),
+            Value::List(items) => Atom::list(all(items)?),
+            Value::Vector(items) => Atom::vector(all(items)?),
+            Value::Set(items) => Atom::set(all(items)?),
+            Value::Map(entries) => Atom::map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| Ok((key.into_atom()?, value.into_atom()?)))
+                    .collect::<Result<Vec<_>, PersistError>>()?,
+            ),
+            Value::Regex(source) => {
+                Atom::Regex(Rc::new(Regex::new(&source).map_err(PersistError::Regex)?))
+            }
+        };
+        Ok(atom)
+    }
+}
+
+/// Why a binding wasn't saved: what it held that can't be.
+pub fn unsaveable(atom: &Atom) -> Option<&'static str> {
+    Value::from_atom(atom).err()
+}
+
+impl ENV {
+    /// Writes every top level binding that's plain data to `path`, returning
+    /// the names of the ones that had to be skipped, each with a description
+    /// of what in it couldn't be saved.
+    pub fn save<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<Vec<(String, &'static str)>, PersistError> {
+        let mut bindings = BTreeMap::new();
+        let mut skipped = vec![];
+        for (name, value) in &self.vars {
+            match Value::from_atom(value) {
+                Ok(value) => {
+                    bindings.insert(name.to_string(), value);
+                }
+                Err(what) => skipped.push((name.to_string(), what)),
+            }
+        }
+        skipped.sort();
+        fs::write(path, serde_json::to_string_pretty(&bindings)?)?;
+        Ok(skipped)
+    }
+
+    /// Defines every binding saved in `path`, returning how many there were.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, PersistError> {
+        let contents = fs::read_to_string(path)?;
+        let bindings: BTreeMap<String, Value> = serde_json::from_str(&contents)?;
+        let count = bindings.len();
+        for (name, value) in bindings {
+            self.define(&name, value.into_atom()?);
+        }
+        Ok(count)
+    }
+}
@@ -0,0 +1,86 @@
+//! Printing values with ANSI colors, for the REPL. The text is the same as
+//! `Display` gives; only the escape codes around it are added.
+
+use crate::atom::Atom;
+
+const KEYWORD: &str = "\x1b[35m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[36m";
+/// `nil`, `true` and `false`.
+const CONSTANT: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrettyPrinter {
+    /// Whether to color anything. Off, `print` is just `to_string`.
+    pub color: bool,
+}
+
+impl PrettyPrinter {
+    pub fn new(color: bool) -> PrettyPrinter {
+        PrettyPrinter { color }
+    }
+
+    pub fn print(&self, atom: &Atom) -> String {
+        let mut out = String::new();
+        self.write(&mut out, atom);
+        out
+    }
+
+    fn write(&self, out: &mut String, atom: &Atom) {
+        let color = match atom {
+            Atom::List(items) => return self.write_seq(out, "(", items.iter(), ")"),
+            Atom::Vector(items) => return self.write_seq(out, "[", items.iter(), "]"),
+            Atom::Set(items) => return self.write_seq(out, "#{", items.iter(), "}"),
+            Atom::Map(map) => {
+                out.push('{');
+                for (i, (key, value)) in map.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    self.write(out, key);
+                    out.push(' ');
+                    self.write(out, value);
+                }
+                out.push('}');
+                return;
+            }
+            Atom::Cell(cell) => {
+                out.push_str("#<atom ");
+                self.write(out, &cell.borrow());
+                out.push('>');
+                return;
+            }
+            Atom::Keyword(_) => Some(KEYWORD),
+            Atom::String(_) | Atom::Char(_) => Some(STRING),
+            Atom::Int(_) | Atom::Float(_) | Atom::Ratio(..) => Some(NUMBER),
+            Atom::Nil | Atom::Boolean(_) => Some(CONSTANT),
+            _ => None,
+        };
+        match color {
+            Some(color) if self.color => {
+                out.push_str(color);
+                out.push_str(&atom.to_string());
+                out.push_str(RESET);
+            }
+            _ => out.push_str(&atom.to_string()),
+        }
+    }
+
+    fn write_seq<'a>(
+        &self,
+        out: &mut String,
+        open: &str,
+        items: impl Iterator<Item = &'a Atom>,
+        close: &str,
+    ) {
+        out.push_str(open);
+        for (i, item) in items.enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            self.write(out, item);
+        }
+        out.push_str(close);
+    }
+}
@@ -0,0 +1,107 @@
+//! The patterns behind `#"..."` literals, compiled with the `regex` crate
+//! when the `regex` feature is on. Its syntax is the crate's: `\d`, `\w` and
+//! `\b` are all Unicode-aware, and matching takes linear time in the input,
+//! so no pattern can blow the stack or backtrack exponentially. Without the
+//! feature every pattern fails to compile, so regex literals are read errors.
+
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub struct RegexError {
+    pub message: String,
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// The text of each group of a match, the whole match first. Groups that
+/// didn't take part in the match are `None`.
+pub type Captures = Vec<Option<String>>;
+
+/// A compiled pattern.
+#[derive(Debug)]
+pub struct Regex {
+    source: String,
+    #[cfg(feature = "regex")]
+    find: ::regex::Regex,
+    /// The same pattern anchored at both ends, for `matches`. Checking the
+    /// span of a leftmost match isn't enough: `a|ab` finds `a` in `ab`.
+    #[cfg(feature = "regex")]
+    whole: ::regex::Regex,
+}
+
+#[cfg(feature = "regex")]
+impl Regex {
+    pub fn new(source: &str) -> Result<Regex, RegexError> {
+        let compile = |pattern: &str| {
+            ::regex::Regex::new(pattern).map_err(|err| RegexError {
+                message: err.to_string(),
+            })
+        };
+        Ok(Regex {
+            source: source.to_string(),
+            find: compile(source)?,
+            whole: compile(&format!(r"\A(?:{})\z", source))?,
+        })
+    }
+
+    /// The number of capturing groups.
+    pub fn groups(&self) -> usize {
+        self.find.captures_len() - 1
+    }
+
+    /// The captures if the pattern matches all of `text`.
+    pub fn matches(&self, text: &str) -> Option<Captures> {
+        self.whole.captures(text).map(|found| captures(&found))
+    }
+
+    /// The captures of the leftmost match in `text`.
+    pub fn find(&self, text: &str) -> Option<Captures> {
+        self.find.captures(text).map(|found| captures(&found))
+    }
+}
+
+#[cfg(feature = "regex")]
+fn captures(found: &::regex::Captures) -> Captures {
+    found
+        .iter()
+        .map(|group| group.map(|group| group.as_str().to_string()))
+        .collect()
+}
+
+#[cfg(not(feature = "regex"))]
+impl Regex {
+    pub fn new(_source: &str) -> Result<Regex, RegexError> {
+        Err(RegexError {
+            message: "warp was built without the regex feature".to_string(),
+        })
+    }
+
+    pub fn groups(&self) -> usize {
+        0
+    }
+
+    pub fn matches(&self, _text: &str) -> Option<Captures> {
+        None
+    }
+
+    pub fn find(&self, _text: &str) -> Option<Captures> {
+        None
+    }
+}
+
+impl Regex {
+    /// The pattern as it was written.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#\"{}\"", self.source)
+    }
+}
@@ -0,0 +1,117 @@
+use std::io::{self, BufRead, Write};
+
+use crate::ast::{LexError, AST};
+use crate::atom::Atom;
+use crate::env::ENV;
+use crate::eval::{eval_defining, EvalError};
+use crate::pretty::PrettyPrinter;
+
+/// An interactive read-eval-print loop over a single environment.
+pub struct Repl {
+    /// Printed before reading each line.
+    pub prompt: String,
+    /// Printed instead of `prompt` while a form is still open, before the
+    /// lines that continue it.
+    pub continuation: String,
+    /// Printed once when the loop starts, if set.
+    pub banner: Option<String>,
+    /// Whether results are printed with ANSI colors. Off by default.
+    pub color: bool,
+    pub env: ENV,
+}
+
+impl Repl {
+    pub fn new() -> Repl {
+        Repl::with_env(ENV::new())
+    }
+
+    pub fn with_env(env: ENV) -> Repl {
+        Repl {
+            prompt: "warp=> ".to_string(),
+            continuation: "  ...> ".to_string(),
+            banner: Some(format!("Warp {}", env!("CARGO_PKG_VERSION"))),
+            color: false,
+            env,
+        }
+    }
+
+    /// Reads lines from `input` until it runs out or `:quit` is entered,
+    /// writing each result (or error) to `output`. A line that leaves a form
+    /// or string open is joined with the lines after it until it's closed.
+    /// Returns the status the session ended with: 0, or the code given to
+    /// `(exit code)`. The loop never exits the process itself; that's up to
+    /// the caller.
+    pub fn run(&mut self, input: &mut dyn BufRead, output: &mut dyn Write) -> io::Result<i32> {
+        if let Some(banner) = &self.banner {
+            writeln!(output, "{}", banner)?;
+        }
+        let mut source = String::new();
+        loop {
+            let prompt = if source.is_empty() {
+                &self.prompt
+            } else {
+                &self.continuation
+            };
+            write!(output, "{}", prompt)?;
+            output.flush()?;
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                return Ok(0);
+            }
+            if source.is_empty() {
+                match line.trim() {
+                    "" => continue,
+                    ":quit" => return Ok(0),
+                    _ => (),
+                }
+            }
+            source.push_str(&line);
+            if is_unfinished(&source) {
+                continue;
+            }
+            match self.eval_line(std::mem::take(&mut source)) {
+                Ok(result) => writeln!(output, "{}", result)?,
+                Err(LineError::Exit(code)) => return Ok(code),
+                Err(LineError::Message(message)) => writeln!(output, "error: {}", message)?,
+            }
+        }
+    }
+
+    fn eval_line(&mut self, line: String) -> Result<String, LineError> {
+        let tokens = AST::tokenize(line).map_err(|err| LineError::Message(err.to_string()))?;
+        let ast = AST::read(&tokens).map_err(|err| LineError::Message(err.to_string()))?;
+        let (result, defined) = eval_defining(&ast, &mut self.env).map_err(|err| match err {
+            EvalError::Exit(code) => LineError::Exit(code),
+            err => LineError::Message(err.to_string()),
+        })?;
+        // `def` and the like return the name they defined, shown as `#'x`
+        match result {
+            Atom::Symbol(name) if defined.contains(&name) => Ok(format!("#'{}", name)),
+            result => Ok(PrettyPrinter::new(self.color).print(&result)),
+        }
+    }
+}
+
+/// Whether `source` only fails to read because it stops inside a form or a
+/// string, so more lines could still finish it.
+fn is_unfinished(source: &str) -> bool {
+    match AST::tokenize(source.to_string()) {
+        Ok(tokens) => matches!(AST::open_forms(&tokens), Ok(open) if open > 0),
+        Err(err) => matches!(err, LexError::UnterminatedString { .. }),
+    }
+}
+
+/// Why a line didn't produce a result.
+enum LineError {
+    /// The line called `exit`, ending the session.
+    Exit(i32),
+    Message(String),
+}
+
+impl Default for Repl {
+    fn default() -> Repl {
+        Repl::new()
+    }
+}
@@ -0,0 +1,50 @@
+//! Running a whole program file with its output captured, for tests that
+//! want to check what a script printed as well as what it returned.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::ast::{LexError, ParseError, AST};
+use crate::atom::Atom;
+use crate::env::ENV;
+use crate::eval::{eval, EvalError};
+
+#[derive(Debug)]
+pub enum RunError {
+    Io(io::Error),
+    Lex(LexError),
+    Parse(ParseError),
+    Eval(EvalError),
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunError::Io(err) => write!(f, "{}", err),
+            RunError::Lex(err) => write!(f, "{}", err),
+            RunError::Parse(err) => write!(f, "{}", err),
+            RunError::Eval(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for RunError {
+    fn from(err: io::Error) -> RunError {
+        RunError::Io(err)
+    }
+}
+
+/// Reads and evaluates the program at `path` in `env`, returning the value
+/// of its last form and everything it printed. Nothing it prints reaches
+/// `env`'s own writer. What it defines stays in `env`, even if it fails
+/// part way through.
+pub fn run_file(path: impl AsRef<Path>, env: &mut ENV) -> Result<(Atom, String), RunError> {
+    let source = fs::read_to_string(path)?;
+    let tokens = AST::tokenize(source).map_err(RunError::Lex)?;
+    let ast = AST::read(&tokens).map_err(RunError::Parse)?;
+    let (result, output) = env.capture_output(|env| eval(&ast, env));
+    let value = result.map_err(RunError::Eval)?;
+    Ok((value, String::from_utf8_lossy(&output).into_owned()))
+}
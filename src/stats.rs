@@ -0,0 +1,45 @@
+use std::collections::BTreeMap;
+
+use crate::ast::{Node, AST};
+
+/// Counts describing how big a program is and how deeply it nests, for
+/// working out what reading it costs.
+#[derive(Debug, Default, PartialEq)]
+pub struct Stats {
+    pub tokens: usize,
+    /// How many forms are inside one another at the deepest point, like 2
+    /// for `(a (b))`. 0 if there are only plain atoms.
+    pub max_depth: usize,
+    /// How many forms of each kind there are, by `SExp::_type`.
+    pub forms: BTreeMap<&'static str, usize>,
+    /// How many plain atoms of each type there are, by `Atom::type_name`.
+    pub atoms: BTreeMap<&'static str, usize>,
+}
+
+impl AST {
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats {
+            tokens: self.tokens,
+            ..Stats::default()
+        };
+        for form in self.forms() {
+            count(form, 0, &mut stats);
+        }
+        stats
+    }
+}
+
+/// Adds `node` and everything in it to `stats`. `depth` is how many forms
+/// `node` is inside.
+fn count(node: Node, depth: usize, stats: &mut Stats) {
+    match node {
+        Node::Form(form) => {
+            *stats.forms.entry(form.kind()).or_insert(0) += 1;
+            stats.max_depth = stats.max_depth.max(depth + 1);
+            for child in form.children() {
+                count(child, depth + 1, stats);
+            }
+        }
+        Node::Atom(atom) => *stats.atoms.entry(atom.type_name()).or_insert(0) += 1,
+    }
+}
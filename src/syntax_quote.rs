@@ -0,0 +1,113 @@
+//! Expanding syntax-quote. `` `form `` reads as `(syntax-quote form)`, which
+//! `macroexpand_1` expands into code that builds `form` when it runs: like
+//! `'form`, except that `~x` puts the value of `x` in, `~@xs` splices every
+//! item of `xs` in, and a symbol ending in `#`, like `x#`, becomes a name no
+//! other code uses, the same one everywhere in the form.
+//!
+//! There are no namespaces, so other symbols are left as they are rather
+//! than qualified. The code the expansion calls is made of builtins, not
+//! symbols, so a local called `list` or `concat` can't change what it does.
+
+use std::collections::HashMap;
+
+use crate::atom::Atom;
+use crate::env::ENV;
+use crate::eval::EvalError;
+
+/// The code that builds `form`, the argument of a `(syntax-quote form)`.
+pub fn syntax_quote(form: &Atom, env: &mut ENV) -> Result<Atom, EvalError> {
+    let mut gensyms = HashMap::new();
+    expand(form, env, &mut gensyms)
+}
+
+/// `gensyms` has the names given to each `x#` so far.
+fn expand(
+    form: &Atom,
+    env: &mut ENV,
+    gensyms: &mut HashMap<String, String>,
+) -> Result<Atom, EvalError> {
+    match form {
+        Atom::Symbol(name) => {
+            let name = match name.strip_suffix('#') {
+                Some(prefix) if !prefix.is_empty() => gensyms
+                    .entry(name.clone())
+                    .or_insert_with(|| env.gensym(prefix))
+                    .clone(),
+                _ => name.clone(),
+            };
+            Ok(Atom::list(vec![
+                Atom::Symbol("quote".to_string()),
+                Atom::Symbol(name),
+            ]))
+        }
+        Atom::List(items) => match unquoted(form) {
+            Some(("unquote", value)) => Ok(value.clone()),
+            Some(_) => Err(splice_outside_collection()),
+            None => Ok(call(
+                "apply",
+                vec![Atom::Builtin("list"), concat(items, env, gensyms)?],
+            )),
+        },
+        Atom::Vector(items) => Ok(call(
+            "apply",
+            vec![Atom::Builtin("vector"), concat(items, env, gensyms)?],
+        )),
+        Atom::Set(items) => {
+            let items: Vec<Atom> = items.iter().cloned().collect();
+            Ok(call("set", vec![concat(&items, env, gensyms)?]))
+        }
+        // each entry is built as a [key value] vector, then poured into a map
+        Atom::Map(map) => {
+            let mut entries = vec![];
+            for (key, value) in map.iter() {
+                let entry = Atom::vector(vec![key.clone(), value.clone()]);
+                entries.push(expand(&entry, env, gensyms)?);
+            }
+            Ok(call("into", vec![Atom::map(vec![]), call("list", entries)]))
+        }
+        // everything else evaluates to itself
+        _ => Ok(form.clone()),
+    }
+}
+
+/// Code concatenating the expansion of each item: the value of `xs` for a
+/// `~@xs` and a one item list of the expansion for anything else.
+fn concat(
+    items: &[Atom],
+    env: &mut ENV,
+    gensyms: &mut HashMap<String, String>,
+) -> Result<Atom, EvalError> {
+    let mut parts = vec![];
+    for item in items {
+        match unquoted(item) {
+            Some(("unquote-splicing", value)) => parts.push(value.clone()),
+            _ => parts.push(call("list", vec![expand(item, env, gensyms)?])),
+        }
+    }
+    Ok(call("concat", parts))
+}
+
+/// The kind and form of a `(unquote x)` or `(unquote-splicing x)`.
+fn unquoted(form: &Atom) -> Option<(&'static str, &Atom)> {
+    let items = match form {
+        Atom::List(items) => items,
+        _ => return None,
+    };
+    match items.as_slice() {
+        [Atom::Symbol(head), value] if head == "unquote" => Some(("unquote", value)),
+        [Atom::Symbol(head), value] if head == "unquote-splicing" => {
+            Some(("unquote-splicing", value))
+        }
+        _ => None,
+    }
+}
+
+fn call(builtin: &'static str, args: Vec<Atom>) -> Atom {
+    let mut call = vec![Atom::Builtin(builtin)];
+    call.extend(args);
+    Atom::list(call)
+}
+
+fn splice_outside_collection() -> EvalError {
+    EvalError::Malformed("~@ can only be used inside a list, vector, map or set".to_string())
+}
@@ -0,0 +1,60 @@
+//! Counts heap allocations, so it runs in its own test binary with a
+//! counting global allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use warp::Atom;
+
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// How many allocations `f` makes on this thread.
+fn allocations<T>(f: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    let count = ALLOCATIONS.with(Cell::get) - before;
+    drop(result);
+    count
+}
+
+#[test]
+fn inferring_numbers_and_literals_does_not_allocate() {
+    for src in ["12345", "-2.5e3", "0x1F", "true", "nil"] {
+        assert_eq!(allocations(|| Atom::infer(src, true)), 0, "{}", src);
+    }
+}
+
+#[test]
+fn inferring_names_allocates_once() {
+    for src in ["name", ":key"] {
+        assert_eq!(allocations(|| Atom::infer(src, true)), 1, "{}", src);
+    }
+}
+
+#[test]
+fn cloning_strings_and_collections_does_not_allocate() {
+    let big = Atom::vector((0..10_000).map(Atom::Int));
+    let text = Atom::string("x".repeat(10_000));
+    let map = Atom::map(vec![(Atom::Int(1), big.clone())]);
+    for atom in [&big, &text, &map] {
+        assert_eq!(allocations(|| atom.clone()), 0, "{}", atom.type_name());
+    }
+}
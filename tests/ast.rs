@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use warp::{eval, Atom, Node, SExp, Severity, AST, ENV};
+
+fn read(src: &str) -> AST {
+    AST::read(&AST::tokenize(src.to_string()).unwrap()).unwrap()
+}
+
+#[test]
+fn check_reports_typos_but_not_locals() {
+    let ast = read("(defn f [x] (let [y (inc x)] (+ y z)))\n(f 1)\n(pritnln 2)");
+    let messages: Vec<String> = ast
+        .check()
+        .into_iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .map(|diagnostic| diagnostic.message)
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "unable to resolve symbol: z",
+            "unable to resolve symbol: pritnln"
+        ]
+    );
+}
+
+#[test]
+fn check_accepts_forward_references() {
+    assert!(read("(defn f [] (g)) (defn g [] 1)").check().is_empty());
+}
+
+#[test]
+fn form_refs_resolve_nested_children() {
+    let ast = read("(f [1 (g :a)] \"s\")");
+    let root = match ast.forms().next() {
+        Some(Node::Form(root)) => root,
+        other => panic!("expected a form, got {:?}", other),
+    };
+    assert_eq!(root.kind(), "exec");
+    let children: Vec<String> = root.children().map(|child| child.to_string()).collect();
+    assert_eq!(children, ["f", "[1 (g :a)]", "\"s\""]);
+
+    let vector = match root.children().nth(1) {
+        Some(Node::Form(vector)) => vector,
+        other => panic!("expected a form, got {:?}", other),
+    };
+    assert_eq!(vector.kind(), "vec");
+    assert_eq!(vector.len(), 2);
+    let inner = vector.children().nth(1);
+    match inner {
+        Some(Node::Form(inner)) => assert_eq!(inner.to_string(), "(g :a)"),
+        other => panic!("expected a form, got {:?}", other),
+    }
+}
+
+/// Builds `(vector [1 2] [1 2])` with both vectors being form 1.
+fn shared_ast() -> AST {
+    let sexp = |_type, children| SExp {
+        _type,
+        children,
+        line: 1,
+        col: 1,
+    };
+    let mut items = HashMap::new();
+    let call = vec![
+        Atom::Symbol("vector".to_string()),
+        Atom::Reference(1),
+        Atom::Reference(1),
+    ];
+    items.insert(0, sexp("exec", call));
+    items.insert(1, sexp("vec", vec![Atom::Int(1), Atom::Int(2)]));
+    AST {
+        items,
+        roots: vec![Atom::Reference(0)],
+        positions: vec![(1, 1)],
+        tokens: 0,
+    }
+}
+
+#[test]
+fn shared_sub_forms_are_resolved_once() {
+    let ast = shared_ast();
+    let form = match ast.form(&ast.roots[0]) {
+        Atom::List(items) => items,
+        other => panic!("expected a list, got {}", other),
+    };
+    match (&form[1], &form[2]) {
+        (Atom::Vector(a), Atom::Vector(b)) => assert!(Rc::ptr_eq(a, b)),
+        other => panic!("expected two vectors, got {:?}", other),
+    }
+    let value = eval(&ast, &mut ENV::new()).unwrap();
+    assert_eq!(value.to_string(), "[[1 2] [1 2]]");
+}
+
+#[test]
+fn walk_visits_every_form() {
+    let mut kinds = vec![];
+    read("(a (b (c)))").walk(|sexp| kinds.push(sexp._type));
+    assert_eq!(kinds, ["exec", "exec", "exec"]);
+
+    let mut count = 0;
+    read("[1 {:a (f)}] #{x}").walk(|_| count += 1);
+    assert_eq!(count, 4);
+}
+
+#[test]
+fn visit_sees_the_atoms_too() {
+    let ast = read("(a (b (c)))");
+    let mut atoms = vec![];
+    let mut forms = 0;
+    ast.visit(|node| match node {
+        Node::Form(_) => forms += 1,
+        Node::Atom(atom) => atoms.push(atom.to_string()),
+    });
+    assert_eq!(forms, 3);
+    assert_eq!(atoms, ["a", "b", "c"]);
+}
+
+#[test]
+fn source_map_has_every_form_at_its_opener() {
+    let ast = read("(f [1 2]\n   {:a #{x}})");
+    let map = ast.source_map();
+    assert_eq!(map.len(), ast.items.len());
+    let mut positions: Vec<(usize, usize)> = map.values().copied().collect();
+    positions.sort();
+    assert_eq!(positions, [(1, 1), (1, 4), (2, 4), (2, 8)]);
+    for (id, position) in &map {
+        let sexp = &ast.items[id];
+        assert_eq!((sexp.line, sexp.col), *position);
+    }
+}
+
+#[test]
+fn top_level_positions_cover_plain_atoms_too() {
+    let ast = read("x\n  (f)  :k\n'y");
+    assert_eq!(ast.positions, [(1, 1), (2, 3), (2, 8), (3, 1)]);
+}
+
+#[test]
+fn stats_count_tokens_depth_and_kinds() {
+    let stats = read("(def x [1 2.5 :k])\n(println (inc x) \"s\")").stats();
+    assert_eq!(stats.tokens, 17);
+    assert_eq!(stats.max_depth, 2);
+    assert_eq!(
+        stats.forms.into_iter().collect::<Vec<_>>(),
+        [("exec", 3), ("vec", 1)]
+    );
+    assert_eq!(
+        stats.atoms.into_iter().collect::<Vec<_>>(),
+        [
+            ("float", 1),
+            ("int", 1),
+            ("keyword", 1),
+            ("string", 1),
+            ("symbol", 5)
+        ]
+    );
+}
+
+#[test]
+fn stats_of_plain_atoms_have_no_depth() {
+    let stats = read("1 a").stats();
+    assert_eq!((stats.tokens, stats.max_depth), (2, 0));
+    assert!(stats.forms.is_empty());
+}
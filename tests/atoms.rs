@@ -0,0 +1,47 @@
+mod common;
+
+use common::{error, show};
+use warp::EvalError;
+
+#[test]
+fn swap_updates_an_atom() {
+    assert_eq!(
+        show("(def a (atom 0)) (swap! a inc) (swap! a + 10) @a"),
+        "11"
+    );
+    assert_eq!(show("(def a (atom 1)) (swap! a + 2)"), "3");
+}
+
+#[test]
+fn reset_and_deref() {
+    assert_eq!(
+        show("(def a (atom :old)) (reset! a :new) (deref a)"),
+        ":new"
+    );
+    assert_eq!(show("(def a (atom 1)) (reset! a 2)"), "2");
+}
+
+#[test]
+fn atoms_are_shared_references() {
+    assert_eq!(
+        show("(def a (atom [])) (def b a) (swap! b conj 1) @a"),
+        "[1]"
+    );
+    assert_eq!(show("(= (atom 1) (atom 1))"), "false");
+}
+
+#[test]
+fn deref_of_something_else_is_a_type_error() {
+    assert!(matches!(
+        error("(deref 1)"),
+        EvalError::Type {
+            expected: "atom",
+            ..
+        }
+    ));
+}
+
+#[test]
+fn atoms_print_their_value() {
+    assert_eq!(show("(atom [1 \"a\"])"), "#<atom [1 \"a\"]>");
+}
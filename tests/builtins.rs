@@ -0,0 +1,111 @@
+mod common;
+
+use common::{output, show};
+
+#[test]
+fn not_equal_two_args() {
+    assert_eq!(show("(not= 1 1)"), "false");
+    assert_eq!(show("(not= 1 2)"), "true");
+}
+
+#[test]
+fn not_equal_many_args() {
+    assert_eq!(show("(not= 1 1 1)"), "false");
+    assert_eq!(show("(not= 1 1 2)"), "true");
+    assert_eq!(show("(not= 1)"), "false");
+}
+
+#[test]
+fn doc_prints_builtin_descriptions() {
+    assert_eq!(
+        output("(doc +)"),
+        "-------------------------\n+\n  Returns the sum of nums. (+) returns 0.\n"
+    );
+}
+
+#[test]
+fn doc_prints_defn_docstrings() {
+    assert_eq!(
+        output("(defn f \"adds one\" [x] (inc x)) (doc f)"),
+        "-------------------------\nf\n  adds one\n"
+    );
+}
+
+#[test]
+fn type_names_each_kind_of_value() {
+    let cases = [
+        ("1", ":int"),
+        ("1.5", ":float"),
+        ("\"s\"", ":string"),
+        ("'x", ":symbol"),
+        (":k", ":keyword"),
+        ("true", ":boolean"),
+        ("nil", ":nil"),
+        ("[1]", ":vector"),
+        ("'(1)", ":list"),
+        ("{:a 1}", ":map"),
+        ("#{1}", ":set"),
+        ("(atom 1)", ":atom"),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(show(&format!("(type {})", src)), expected, "{}", src);
+    }
+}
+
+#[test]
+fn every_kind_of_function_is_a_function() {
+    for src in [
+        "inc",
+        "(fn [x] x)",
+        "#(+ % 1)",
+        "(comp inc inc)",
+        "(partial + 1)",
+    ] {
+        assert_eq!(show(&format!("(type {})", src)), ":function", "{}", src);
+    }
+}
+
+#[test]
+fn name_and_namespace_of_qualified_names() {
+    assert_eq!(show("(name :foo/bar)"), "\"bar\"");
+    assert_eq!(show("(namespace :foo/bar)"), "\"foo\"");
+    assert_eq!(show("(name 'foo/bar)"), "\"bar\"");
+    assert_eq!(show("(namespace 'foo/bar)"), "\"foo\"");
+}
+
+#[test]
+fn name_and_namespace_of_unqualified_names() {
+    assert_eq!(show("(name :bar)"), "\"bar\"");
+    assert_eq!(show("(namespace :bar)"), "nil");
+    assert_eq!(show("(name 'bar)"), "\"bar\"");
+    assert_eq!(show("(namespace 'bar)"), "nil");
+    assert_eq!(show("(name \"s\")"), "\"s\"");
+}
+
+#[test]
+fn keyword_and_symbol_from_one_string() {
+    assert_eq!(show("(keyword \"foo\")"), ":foo");
+    assert_eq!(show("(symbol \"foo\")"), "foo");
+    assert_eq!(show("(= (symbol \"foo\") 'foo)"), "true");
+    assert_eq!(show("(keyword 'foo)"), ":foo");
+}
+
+#[test]
+fn keyword_and_symbol_with_a_namespace() {
+    assert_eq!(show("(keyword \"ns\" \"foo\")"), ":ns/foo");
+    assert_eq!(show("(symbol \"ns\" \"foo\")"), "ns/foo");
+    assert_eq!(show("(namespace (keyword \"ns\" \"foo\"))"), "\"ns\"");
+    assert_eq!(show("(keyword nil \"foo\")"), ":foo");
+}
+
+#[test]
+fn resolve_returns_the_bound_value_or_nil() {
+    assert_eq!(show("(resolve 'undefined)"), "nil");
+    assert_eq!(show("(def x 1) (resolve 'x)"), "1");
+    assert_eq!(show("((resolve 'inc) 1)"), "2");
+}
+
+#[test]
+fn resolve_doesnt_see_locals() {
+    assert_eq!(show("(let [y 1] (resolve 'y))"), "nil");
+}
@@ -0,0 +1,138 @@
+//! Runs the `warp` binary itself.
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Runs warp with `args`, piping `stdin` in.
+fn warp(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_warp"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+fn stderr(output: &Output) -> &str {
+    std::str::from_utf8(&output.stderr).unwrap()
+}
+
+#[test]
+fn runs_a_program_piped_to_stdin() {
+    let output = warp(&[], "(println \"hi\")\n(+ 1 2)");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "hi\n3\n");
+}
+
+#[test]
+fn dash_reads_the_program_from_stdin() {
+    let output = warp(&["-"], "[1 \"two\"]");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "[1 \"two\"]\n");
+}
+
+#[test]
+fn a_missing_file_is_reported_without_panicking() {
+    let output = warp(&["/nonexistent/warp-script.clj"], "");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = stderr(&output);
+    assert!(
+        stderr.starts_with("could not read /nonexistent/warp-script.clj: "),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+}
+
+#[test]
+fn print_ast_shows_the_forms_without_evaluating() {
+    let output = warp(&["--print-ast", "-"], "(def x [1 {:a \"s\"}])\n(println x)");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "(def x [1 {:a \"s\"}])\n(println x)\n");
+}
+
+#[test]
+fn print_tokens_lists_each_token_with_its_position() {
+    let output = warp(&["--print-tokens", "-"], "(inc\n  \"s\")");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "1:1\t(\n1:2\tinc\n2:3\t\"s\"\n2:6\t)\n");
+}
+
+#[test]
+fn exit_ends_a_script_with_its_status() {
+    let output = warp(
+        &["-"],
+        "(println \"before\")\n(exit 0)\n(println \"after\")",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "before\n");
+
+    let output = warp(&["-"], "(exit 3)");
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn exit_with_no_code_is_success() {
+    let output = warp(&["-"], "(exit)\n(println \"after\")");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn json_diagnostics_for_a_parse_error() {
+    let output = warp(&["--diagnostics=json", "-"], "(+ 1 2))");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "{\"severity\":\"error\",\"line\":1,\"col\":8,\"message\":\"unexpected ')'\"}\n"
+    );
+}
+
+#[test]
+fn json_diagnostics_for_a_lex_error() {
+    let output = warp(&["--diagnostics=json", "-"], "(println\n  \"open)");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "{\"severity\":\"error\",\"line\":2,\"col\":3,\"message\":\"unterminated string\"}\n"
+    );
+}
+
+#[test]
+fn json_diagnostics_for_an_eval_error_give_its_form() {
+    let output = warp(&["--diagnostics=json", "-"], "(def x 1)\n  (inc \"a\")");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "{\"severity\":\"error\",\"line\":2,\"col\":3,\"message\":\"expected number, got string\"}\n"
+    );
+
+    let output = warp(&["--diagnostics=json", "-"], "1 nope");
+    assert_eq!(
+        stderr(&output),
+        "{\"severity\":\"error\",\"line\":1,\"col\":3,\"message\":\"unable to resolve symbol: nope\"}\n"
+    );
+}
+
+#[test]
+fn text_diagnostics_give_the_position_too() {
+    let output = warp(&["-"], "\n(undefined-fn 1)");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "error: 2:1: unable to resolve symbol: undefined-fn\n"
+    );
+}
@@ -0,0 +1,409 @@
+mod common;
+
+use common::{error, show};
+use warp::EvalError;
+
+#[test]
+fn set_literals_hold_their_elements() {
+    assert_eq!(show("(count #{1 2 3})"), "3");
+    assert_eq!(show("(contains? #{1 2 3} 2)"), "true");
+    assert_eq!(show("(contains? #{1 2 3} 4)"), "false");
+}
+
+#[test]
+fn conj_and_disj_dedup() {
+    assert_eq!(show("(count (conj #{1 2} 2 3))"), "3");
+    assert_eq!(show("(= (disj #{1 2 3} 2 4) #{1 3})"), "true");
+}
+
+#[test]
+fn computed_duplicates_in_a_set_literal_collapse() {
+    assert_eq!(show("(count #{(+ 1 1) 2})"), "1");
+}
+
+#[test]
+fn contains_checks_map_keys() {
+    assert_eq!(show("(contains? {:a 1} :a)"), "true");
+    assert_eq!(show("(contains? {:a nil} :a)"), "true");
+    assert_eq!(show("(contains? {:a 1} 1)"), "false");
+}
+
+#[test]
+fn contains_checks_set_membership() {
+    assert_eq!(show("(contains? #{:a} :a)"), "true");
+    assert_eq!(show("(contains? #{:a} :b)"), "false");
+}
+
+/// Like in Clojure, `contains?` on a vector asks whether the index is valid,
+/// not whether the value is in it.
+#[test]
+fn contains_on_a_vector_checks_the_index() {
+    assert_eq!(show("(contains? [:a :b :c] 2)"), "true");
+    assert_eq!(show("(contains? [:a :b :c] 3)"), "false");
+    assert_eq!(show("(contains? [:a :b :c] :a)"), "false");
+    assert_eq!(show("(contains? [10 20] 10)"), "false");
+}
+
+#[test]
+fn keys_and_vals_pair_up() {
+    let src = "(def m {:a 1 :b 2 :c 3})
+               (= (zipmap (keys m) (vals m)) m)";
+    assert_eq!(show(src), "true");
+    assert_eq!(show("(keys {})"), "[]");
+}
+
+#[test]
+fn merge_prefers_later_maps() {
+    assert_eq!(
+        show("(merge {:a 1 :b 2} {:b 3} {:c 4})"),
+        "{:a 1, :b 3, :c 4}"
+    );
+}
+
+#[test]
+fn merge_ignores_nil() {
+    assert_eq!(show("(merge)"), "nil");
+    assert_eq!(show("(merge nil)"), "nil");
+    assert_eq!(show("(merge {:a 1} nil {:b 2})"), "{:a 1, :b 2}");
+    assert_eq!(show("(merge {:a 1})"), "{:a 1}");
+}
+
+#[test]
+fn empty_across_collection_types() {
+    for empty in ["[]", "'()", "{}", "#{}", "\"\"", "nil"] {
+        assert_eq!(show(&format!("(empty? {})", empty)), "true", "{}", empty);
+    }
+    for full in ["[1]", "'(1)", "{:a 1}", "#{1}", "\"a\""] {
+        assert_eq!(show(&format!("(empty? {})", full)), "false", "{}", full);
+    }
+}
+
+#[test]
+fn seq_is_nil_for_empty_collections() {
+    for empty in ["[]", "'()", "{}", "#{}", "\"\"", "nil"] {
+        assert_eq!(show(&format!("(seq {})", empty)), "nil", "{}", empty);
+    }
+    assert_eq!(show("(seq [1 2])"), "[1 2]");
+    assert_eq!(show("(if (seq [1]) :some :none)"), ":some");
+}
+
+#[test]
+fn into_a_set_dedups() {
+    assert_eq!(show("(= (into #{} [1 2 2 3 1]) #{1 2 3})"), "true");
+    assert_eq!(show("(count (into #{1} [1 2]))"), "2");
+}
+
+#[test]
+fn into_a_map_takes_pairs() {
+    assert_eq!(show("(into {} [[:a 1] [:b 2]])"), "{:a 1, :b 2}");
+    assert_eq!(show("(into {:a 1} {:a 3 :c 4})"), "{:a 3, :c 4}");
+}
+
+#[test]
+fn into_vectors_appends_and_into_lists_prepends() {
+    assert_eq!(show("(into [1] '(2 3))"), "[1 2 3]");
+    assert_eq!(show("(into '(1) [2 3])"), "(3 2 1)");
+}
+
+#[test]
+fn sort_ints_strings_and_keywords() {
+    assert_eq!(show("(sort [3 1 2.5 -4 1/2])"), "[-4 1/2 1 2.5 3]");
+    assert_eq!(show("(sort [\"b\" \"a\" \"c\"])"), "[\"a\" \"b\" \"c\"]");
+    assert_eq!(show("(sort #{:b :a})"), "[:a :b]");
+    assert_eq!(show("(sort [])"), "[]");
+}
+
+#[test]
+fn sort_by_a_key_function_is_stable() {
+    assert_eq!(
+        show("(sort-by count [\"ccc\" \"a\" \"bb\"])"),
+        "[\"a\" \"bb\" \"ccc\"]"
+    );
+    assert_eq!(
+        show("(sort-by :n [{:n 2 :id 1} {:n 1 :id 2} {:n 2 :id 3}])"),
+        "[{:n 1, :id 2} {:n 2, :id 1} {:n 2, :id 3}]"
+    );
+}
+
+#[test]
+fn sorting_incomparable_values_fails() {
+    for src in [
+        "(sort [1 \"a\" 2])",
+        "(sort [\"a\" \"b\" 3 \"c\" 1])",
+        "(sort-by :k [{:k 1} {:k :x}])",
+    ] {
+        assert!(matches!(error(src), EvalError::Type { .. }), "{}", src);
+    }
+}
+
+#[test]
+fn reverse_sequences_and_strings() {
+    assert_eq!(show("(reverse [1 2 3])"), "[3 2 1]");
+    assert_eq!(show("(reverse '(1 2 3))"), "(3 2 1)");
+    assert_eq!(show("(reverse \"héllo\")"), "\"olléh\"");
+    assert_eq!(show("(reverse [])"), "[]");
+}
+
+#[test]
+fn vec_and_set_convert_collections() {
+    assert_eq!(show("(vec '(1 2 3))"), "[1 2 3]");
+    assert_eq!(show("(vec nil)"), "[]");
+    assert_eq!(show("(= (set [1 2 2 3 3 3]) #{1 2 3})"), "true");
+    assert_eq!(show("(count (set [1 2 2 3 3 3]))"), "3");
+}
+
+#[test]
+fn frequencies_counts_each_element() {
+    assert_eq!(show("(frequencies [:a :b :a 1 :a 1])"), "{:a 3, :b 1, 1 2}");
+    assert_eq!(show("(frequencies [])"), "{}");
+}
+
+#[test]
+fn group_by_collects_elements_by_key() {
+    assert_eq!(
+        show("(group-by even? [1 2 3 4 5])"),
+        "{false [1 3 5], true [2 4]}"
+    );
+    assert_eq!(
+        show("(group-by count [\"a\" \"bb\" \"c\"])"),
+        "{1 [\"a\" \"c\"], 2 [\"bb\"]}"
+    );
+}
+
+#[test]
+fn conj_leaves_the_original_unchanged() {
+    assert_eq!(
+        show("(def v [1 2]) (def w (conj v 3)) [v w]"),
+        "[[1 2] [1 2 3]]"
+    );
+    assert_eq!(show("(def l '(1 2)) (conj l 0) l"), "(1 2)");
+    assert_eq!(show("(def s #{1}) (conj s 2) s"), "#{1}");
+    assert_eq!(
+        show("(def m {:a 1}) (def n (conj m [:b 2])) [m n]"),
+        "[{:a 1} {:a 1, :b 2}]"
+    );
+}
+
+#[test]
+fn updating_a_map_leaves_the_original_unchanged() {
+    assert_eq!(
+        show("(def m {:a {:b 1}}) (def n (assoc-in m [:a :b] 2)) [m n]"),
+        "[{:a {:b 1}} {:a {:b 2}}]"
+    );
+    assert_eq!(show("(def m {:a 1}) (update m :a inc) m"), "{:a 1}");
+}
+
+#[test]
+fn chained_conjes_build_one_vector() {
+    assert_eq!(
+        show("(def v [0]) (count (conj (conj (conj v 1) 2) 3))"),
+        "4"
+    );
+    assert_eq!(show("(def v [0]) (conj (conj (conj v 1) 2) 3) v"), "[0]");
+}
+
+#[test]
+fn peek_and_pop_use_the_end_a_vector_grows_at() {
+    assert_eq!(show("(peek [1 2 3])"), "3");
+    assert_eq!(show("(pop [1 2 3])"), "[1 2]");
+    assert_eq!(show("(peek (conj [1] 2))"), "2");
+}
+
+#[test]
+fn peek_and_pop_use_the_front_of_a_list() {
+    assert_eq!(show("(peek '(1 2 3))"), "1");
+    assert_eq!(show("(pop '(1 2 3))"), "(2 3)");
+    assert_eq!(show("(peek (conj '(1) 2))"), "2");
+}
+
+#[test]
+fn peeking_empty_is_nil_but_popping_empty_is_an_error() {
+    assert_eq!(show("(peek [])"), "nil");
+    assert_eq!(show("(peek '())"), "nil");
+    assert_eq!(show("(peek nil)"), "nil");
+    assert_eq!(error("(pop [])"), EvalError::EmptyPop("vector"));
+    assert_eq!(error("(pop '())"), EvalError::EmptyPop("list"));
+}
+
+#[test]
+fn take_and_drop() {
+    assert_eq!(show("(take 2 [1 2 3])"), "[1 2]");
+    assert_eq!(show("(take 2 '(1 2 3))"), "[1 2]");
+    assert_eq!(show("(drop 2 [1 2 3])"), "[3]");
+    assert_eq!(show("(drop 1 '(1 2 3))"), "[2 3]");
+}
+
+#[test]
+fn taking_or_dropping_more_than_there_is() {
+    assert_eq!(show("(take 10 [1 2 3])"), "[1 2 3]");
+    assert_eq!(show("(drop 10 [1 2 3])"), "[]");
+    assert_eq!(show("(take -1 [1 2 3])"), "[]");
+    assert_eq!(show("(drop -1 [1 2 3])"), "[1 2 3]");
+}
+
+#[test]
+fn take_while_and_drop_while() {
+    assert_eq!(show("(take-while #(< % 3) [1 2 3 1])"), "[1 2]");
+    assert_eq!(show("(drop-while #(< % 3) [1 2 3 1])"), "[3 1]");
+    assert_eq!(show("(take-while pos? '())"), "[]");
+    assert_eq!(show("(drop-while pos? [1 2])"), "[]");
+}
+
+#[test]
+fn zipmap_pairs_keys_with_vals() {
+    assert_eq!(show("(zipmap [:a :b :c] [1 2 3])"), "{:a 1, :b 2, :c 3}");
+    assert_eq!(show("(zipmap [:a :b :c] [1 2])"), "{:a 1, :b 2}");
+    assert_eq!(show("(zipmap [] [1])"), "{}");
+}
+
+#[test]
+fn interleave_alternates() {
+    assert_eq!(show("(interleave [1 2 3] [:a :b :c])"), "[1 :a 2 :b 3 :c]");
+    assert_eq!(show("(interleave [1 2 3] [:a])"), "[1 :a]");
+    assert_eq!(show("(interleave [1 2] '(3 4) [5 6])"), "[1 3 5 2 4 6]");
+}
+
+#[test]
+fn distinct_keeps_first_occurrences_in_order() {
+    assert_eq!(show("(distinct [3 1 3 2 1 4])"), "[3 1 2 4]");
+    assert_eq!(show("(distinct '(:a \"a\" :a \"a\"))"), "[:a \"a\"]");
+}
+
+#[test]
+fn distinct_uses_value_equality() {
+    assert_eq!(
+        show("(distinct [[1 2] [1 2] {:a 1} {:a 1}])"),
+        "[[1 2] {:a 1}]"
+    );
+    assert_eq!(show("(distinct [1 1.0])"), "[1 1.0]");
+}
+
+#[test]
+fn maps_keep_insertion_order() {
+    assert_eq!(show("(keys {:a 1 :b 2 :c 3})"), "[:a :b :c]");
+    assert_eq!(show("(vals {:c 3 :a 1 :b 2})"), "[3 1 2]");
+    assert_eq!(show("(conj {:z 1 :y 2} [:x 3])"), "{:z 1, :y 2, :x 3}");
+}
+
+#[test]
+fn replacing_a_value_keeps_its_place() {
+    assert_eq!(show("(keys (conj {:a 1 :b 2} [:a 3]))"), "[:a :b]");
+    assert_eq!(show("(conj {:a 1 :b 2} [:a 3])"), "{:a 3, :b 2}");
+}
+
+#[test]
+fn update_applies_f_to_one_value() {
+    assert_eq!(show("(update {:a 1} :a inc)"), "{:a 2}");
+    assert_eq!(show("(update {:a 1} :a + 10 100)"), "{:a 111}");
+    assert_eq!(show("(update {} :n (fn [old] (if old old 0)))"), "{:n 0}");
+}
+
+#[test]
+fn nested_updates() {
+    assert_eq!(show("(update-in {:a {:b 1}} [:a :b] inc)"), "{:a {:b 2}}");
+    assert_eq!(
+        show("(assoc-in {:a {:b 1}} [:a :c] 2)"),
+        "{:a {:b 1, :c 2}}"
+    );
+}
+
+#[test]
+fn nested_updates_make_missing_maps() {
+    assert_eq!(show("(assoc-in {} [:a :b] 1)"), "{:a {:b 1}}");
+    assert_eq!(
+        show("(update-in {} [:a :b] (fn [old] (if old old :new)))"),
+        "{:a {:b :new}}"
+    );
+}
+
+#[test]
+fn get_in_follows_maps_and_vector_indices() {
+    assert_eq!(show("(get-in {:a {:b 1}} [:a :b])"), "1");
+    assert_eq!(show("(get-in {:a [10 {:c 3}]} [:a 1 :c])"), "3");
+    assert_eq!(show("(get-in {:a 1} [])"), "{:a 1}");
+}
+
+#[test]
+fn get_in_of_a_missing_path_is_nil() {
+    assert_eq!(show("(get-in {:a {:b 1}} [:a :x])"), "nil");
+    assert_eq!(show("(get-in {:a 1} [:a :b])"), "nil");
+    assert_eq!(show("(get-in [1 2] [5])"), "nil");
+}
+
+#[test]
+fn get_in_with_a_default() {
+    assert_eq!(show("(get-in {:a {}} [:a :b] :none)"), ":none");
+    assert_eq!(show("(get-in {:a {:b nil}} [:a :b] :none)"), "nil");
+}
+
+#[test]
+fn repeat_makes_n_copies() {
+    assert_eq!(show("(repeat 3 :x)"), "[:x :x :x]");
+    assert_eq!(show("(repeat 0 :x)"), "[]");
+    assert_eq!(show("(repeat -2 :x)"), "[]");
+}
+
+#[test]
+fn repeatedly_calls_f_n_times() {
+    assert_eq!(
+        show("(def calls (atom 0)) [(repeatedly 4 #(swap! calls inc)) @calls]"),
+        "[[1 2 3 4] 4]"
+    );
+    assert_eq!(
+        show("(def calls (atom 0)) (repeatedly -1 #(swap! calls inc)) @calls"),
+        "0"
+    );
+}
+
+#[test]
+fn iterate_applies_f_over_and_over() {
+    assert_eq!(show("(iterate inc 0 5)"), "[0 1 2 3 4]");
+    assert_eq!(show("(iterate #(* 2 %) 1 4)"), "[1 2 4 8]");
+}
+
+#[test]
+fn iterate_of_no_items() {
+    assert_eq!(show("(iterate inc 0 0)"), "[]");
+    assert_eq!(show("(iterate inc 0 -1)"), "[]");
+    assert_eq!(show("(iterate inc 0 1)"), "[0]");
+}
+
+#[test]
+fn flatten_inlines_nested_sequences() {
+    assert_eq!(show("(flatten [1 [2 [3]] 4])"), "[1 2 3 4]");
+    assert_eq!(show("(flatten '(1 (2 [3 (4)])))"), "[1 2 3 4]");
+    assert_eq!(show("(flatten [[] [[]]])"), "[]");
+}
+
+#[test]
+fn flatten_leaves_maps_and_sets_whole() {
+    assert_eq!(show("(flatten [{:a [1]} [#{2}]])"), "[{:a [1]} #{2}]");
+    assert_eq!(show("(flatten 5)"), "[]");
+}
+
+#[test]
+fn mapcat_concatenates_the_results() {
+    assert_eq!(show("(mapcat (fn [x] [x x]) [1 2])"), "[1 1 2 2]");
+    assert_eq!(
+        show("(mapcat (fn [x] (list x (* 10 x))) '(1 2))"),
+        "[1 10 2 20]"
+    );
+    assert_eq!(show("(mapcat (fn [x] []) [1 2])"), "[]");
+    assert_eq!(show("(mapcat reverse [[1 2] [3]])"), "[2 1 3]");
+}
+
+#[test]
+fn some_returns_the_first_truthy_result() {
+    assert_eq!(show("(some even? [1 3 4 5])"), "true");
+    assert_eq!(show("(some #(if (> % 2) (* % 10) nil) [1 3 4])"), "30");
+    assert_eq!(show("(some even? [1 3 5])"), "nil");
+    assert_eq!(show("(some even? [])"), "nil");
+}
+
+#[test]
+fn every_checks_all_items() {
+    assert_eq!(show("(every? even? [2 4 6])"), "true");
+    assert_eq!(show("(every? even? [2 3 6])"), "false");
+    assert_eq!(show("(every? odd? [2 4])"), "false");
+    assert_eq!(show("(every? even? [])"), "true");
+}
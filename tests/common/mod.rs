@@ -0,0 +1,43 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use warp::{eval, Atom, EvalError, AST, ENV};
+
+/// Reads and evaluates `src` in `env`. Lex and parse errors panic, since the
+/// tests that want them call the reader directly.
+pub fn eval_in(src: &str, env: &mut ENV) -> Result<Atom, EvalError> {
+    let tokens = AST::tokenize(src.to_string()).expect("lex error");
+    let ast = AST::read(&tokens).expect("parse error");
+    eval(&ast, env)
+}
+
+/// Evaluates `src` in a fresh ENV.
+pub fn eval_str(src: &str) -> Result<Atom, EvalError> {
+    eval_in(src, &mut ENV::new())
+}
+
+/// The value of `src`, printed readably.
+pub fn show(src: &str) -> String {
+    match eval_str(src) {
+        Ok(value) => value.readable().to_string(),
+        Err(err) => panic!("{} failed: {}", src, err),
+    }
+}
+
+/// The error `src` fails with.
+pub fn error(src: &str) -> EvalError {
+    match eval_str(src) {
+        Ok(value) => panic!("{} gave {} instead of failing", src, value),
+        Err(err) => err,
+    }
+}
+
+/// What `src` writes to its output. Evaluation errors panic.
+pub fn output(src: &str) -> String {
+    let mut env = ENV::new();
+    let (result, out) = env.capture_output(|env| eval_in(src, env));
+    if let Err(err) = result {
+        panic!("{} failed: {}", src, err);
+    }
+    String::from_utf8(out).unwrap()
+}
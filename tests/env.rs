@@ -0,0 +1,123 @@
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use common::eval_in;
+use warp::{Atom, EvalError, ENV};
+
+fn show_in(src: &str, env: &mut ENV) -> String {
+    eval_in(src, env).unwrap().readable().to_string()
+}
+
+#[test]
+fn restore_forgets_defs_made_after_the_snapshot() {
+    let mut env = ENV::new();
+    eval_in("(def kept 1)", &mut env).unwrap();
+    let snapshot = env.snapshot();
+    eval_in("(def scratch 2) (def kept 3)", &mut env).unwrap();
+    env.restore(snapshot);
+    assert_eq!(show_in("kept", &mut env), "1");
+    assert_eq!(
+        eval_in("scratch", &mut env),
+        Err(EvalError::Unbound("scratch".to_string()))
+    );
+}
+
+#[test]
+fn a_snapshot_can_be_restored_more_than_once() {
+    let mut env = ENV::new();
+    let snapshot = env.snapshot();
+    for i in 0..2 {
+        eval_in(&format!("(def x {})", i), &mut env).unwrap();
+        env.restore(snapshot.clone());
+        assert!(eval_in("x", &mut env).is_err());
+    }
+}
+
+#[test]
+fn atoms_are_shared_with_the_snapshot() {
+    let mut env = ENV::new();
+    eval_in("(def counter (atom 0))", &mut env).unwrap();
+    let snapshot = env.snapshot();
+    eval_in("(swap! counter inc)", &mut env).unwrap();
+    env.restore(snapshot);
+    assert_eq!(show_in("@counter", &mut env), "1");
+}
+
+fn double(args: Vec<Atom>, _: &mut ENV) -> Result<Atom, EvalError> {
+    match args.as_slice() {
+        [Atom::Int(n)] => Ok(Atom::Int(n * 2)),
+        _ => Err(EvalError::Arity {
+            name: "double".to_string(),
+            got: args.len(),
+        }),
+    }
+}
+
+#[test]
+fn registered_builtins_are_callable_from_source() {
+    let mut env = ENV::new();
+    env.register_builtin("double", double);
+    assert_eq!(show_in("(double 21)", &mut env), "42");
+    assert_eq!(
+        show_in("(vec (mapcat #(list (double %)) [1 2]))", &mut env),
+        "[2 4]"
+    );
+    assert!(matches!(
+        eval_in("(double)", &mut env),
+        Err(EvalError::Arity { .. })
+    ));
+}
+
+#[test]
+fn a_registered_builtin_replaces_the_standard_one() {
+    let mut env = ENV::new();
+    env.register_builtin("inc", double);
+    assert_eq!(show_in("(inc 5)", &mut env), "10");
+    // other envs still have their own tables
+    assert_eq!(show_in("(inc 5)", &mut ENV::new()), "6");
+}
+
+#[test]
+fn defs_shadow_registered_builtins() {
+    let mut env = ENV::new();
+    env.register_builtin("double", double);
+    eval_in("(def double (fn [n] (* n 3)))", &mut env).unwrap();
+    assert_eq!(show_in("(double 2)", &mut env), "6");
+}
+
+#[test]
+fn native_closures_can_keep_host_state() {
+    let mut env = ENV::new();
+    let seen = Rc::new(RefCell::new(vec![]));
+    let log = seen.clone();
+    env.define_native("log!", move |args| {
+        log.borrow_mut()
+            .extend(args.iter().map(|arg| arg.to_string()));
+        Ok(Atom::Int(log.borrow().len() as i64))
+    });
+    assert_eq!(show_in("(log! 1 :a)", &mut env), "2");
+    assert_eq!(show_in("(log! \"b\")", &mut env), "3");
+    assert_eq!(*seen.borrow(), ["1", ":a", "b"]);
+}
+
+#[test]
+fn native_closures_are_plain_definitions() {
+    let mut env = ENV::new();
+    let offset = 10;
+    env.define_native("shift", move |args| match args {
+        [Atom::Int(n)] => Ok(Atom::Int(n + offset)),
+        _ => Err(EvalError::Arity {
+            name: "shift".to_string(),
+            got: args.len(),
+        }),
+    });
+    assert_eq!(
+        show_in("(vec (mapcat #(list (shift %)) [1 2]))", &mut env),
+        "[11 12]"
+    );
+    assert_eq!(show_in("shift", &mut env), "#<fn shift>");
+    eval_in("(def shift inc)", &mut env).unwrap();
+    assert_eq!(show_in("(shift 1)", &mut env), "2");
+}
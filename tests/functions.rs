@@ -0,0 +1,173 @@
+mod common;
+
+use common::{error, eval_in, show};
+use warp::ENV;
+
+#[test]
+fn fn_literal_with_one_arg() {
+    assert_eq!(show("(#(+ % 1) 4)"), "5");
+    assert_eq!(show("(#(+ %1 1) 4)"), "5");
+}
+
+#[test]
+fn fn_literal_with_numbered_and_rest_args() {
+    assert_eq!(show("(#(- %2 %1) 1 10)"), "9");
+    assert_eq!(show("(#(vector % %&) 1 2 3)"), "[1 (2 3)]");
+}
+
+#[test]
+fn juxt_returns_a_vector_of_results() {
+    assert_eq!(show("((juxt inc dec) 5)"), "[6 4]");
+}
+
+#[test]
+fn partial_captures_leading_args() {
+    assert_eq!(show("((partial + 10) 5)"), "15");
+    assert_eq!(show("((partial vector 1 2) 3 4)"), "[1 2 3 4]");
+}
+
+#[test]
+fn partial_composes_with_apply() {
+    assert_eq!(show("(apply (partial + 10) [1 2 3])"), "16");
+    assert_eq!(show("(apply partial [+ 1 2])"), "#<fn partial>");
+}
+
+#[test]
+fn comp_applies_right_to_left() {
+    assert_eq!(show("((comp inc (partial * 2)) 5)"), "11");
+    assert_eq!(show("((comp vector inc (partial * 2)) 5)"), "[11]");
+}
+
+#[test]
+fn comp_of_nothing_is_identity() {
+    assert_eq!(show("((comp) 5)"), "5");
+    assert_eq!(show("((comp) [1 2])"), "[1 2]");
+}
+
+#[test]
+fn identity_returns_its_argument() {
+    assert_eq!(show("(identity 3)"), "3");
+    assert_eq!(show("(identity [1 {:a nil}])"), "[1 {:a nil}]");
+}
+
+#[test]
+fn constantly_ignores_its_arguments() {
+    assert_eq!(show("((constantly 7) 1 2 3)"), "7");
+    assert_eq!(show("((constantly 7))"), "7");
+}
+
+#[test]
+fn keywords_look_themselves_up() {
+    assert_eq!(show("(:a {:a 1})"), "1");
+    assert_eq!(show("(:b {:a 1})"), "nil");
+    assert_eq!(show("(:b {:a 1} :default)"), ":default");
+    assert_eq!(show("((comp :a :b) {:b {:a 1}})"), "1");
+}
+
+#[test]
+fn maps_look_up_their_keys() {
+    assert_eq!(show("({:a 1} :a)"), "1");
+    assert_eq!(show("({:a 1} :b)"), "nil");
+    assert_eq!(show("({:a 1} :b 0)"), "0");
+}
+
+#[test]
+fn recur_in_tail_position_loops() {
+    assert_eq!(
+        show("(loop [i 0 acc 0] (if (> i 4) acc (recur (+ i 1) (+ acc i))))"),
+        "10"
+    );
+    assert_eq!(
+        show("(defn count-down [n] (if (= n 0) :done (do (recur (- n 1))))) (count-down 100000)"),
+        ":done"
+    );
+    assert_eq!(
+        show("(loop [x 3] (let [y (- x 1)] (if (< y 0) x (recur y))))"),
+        "0"
+    );
+}
+
+#[test]
+fn recur_outside_tail_position_is_rejected() {
+    let expected = "recur: can only be used in tail position of a loop or fn";
+    assert_eq!(
+        error("(defn f [n] (+ 1 (recur (- n 1))))").to_string(),
+        expected
+    );
+    assert_eq!(
+        error("(loop [i 0] (do (recur (+ i 1)) i))").to_string(),
+        expected
+    );
+    assert_eq!(
+        error("(loop [i 0] (if (recur i) 1 2))").to_string(),
+        expected
+    );
+}
+
+#[test]
+fn memoize_runs_f_once_per_distinct_argument() {
+    assert_eq!(
+        show(
+            "(def calls (atom 0))
+             (def slow-inc (memoize (fn [x] (swap! calls inc) (+ x 1))))
+             [(slow-inc 1) (slow-inc 1) (slow-inc 2) (slow-inc 1) @calls]"
+        ),
+        "[2 2 3 2 2]"
+    );
+}
+
+#[test]
+fn memoize_keys_on_the_whole_argument_list() {
+    assert_eq!(
+        show(
+            "(def calls (atom 0))
+             (def f (memoize (fn [& args] (swap! calls inc) (count args))))
+             [(f) (f 1 2) (f [1 2]) (f 1 2) (f [1 2]) @calls]"
+        ),
+        "[0 2 1 2 1 3]"
+    );
+}
+
+fn trace(src: &str) -> String {
+    let mut env = ENV::new();
+    env.trace = true;
+    let (result, out) = env.capture_output(|env| eval_in(src, env));
+    result.unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn traced_calls_nest_under_the_call_they_are_arguments_of() {
+    assert_eq!(
+        trace("(+ 1 (* 2 3))"),
+        "(+ 1 (* 2 3))\n  (* 2 3)\n  => 6\n=> 7\n"
+    );
+}
+
+#[test]
+fn traced_calls_nest_under_the_function_they_are_in() {
+    assert_eq!(
+        trace("(defn sq [x] (* x x)) (sq (inc 1))"),
+        "(sq (inc 1))\n  (inc 1)\n  => 2\n  (* x x)\n  => 4\n=> 4\n"
+    );
+}
+
+#[test]
+fn calls_made_by_builtins_are_traced_with_their_arguments() {
+    assert_eq!(
+        trace("(apply inc [1])"),
+        "(apply inc [1])\n  (inc 1)\n  => 2\n=> 2\n"
+    );
+}
+
+#[test]
+fn traced_errors_are_marked() {
+    let mut env = ENV::new();
+    env.trace = true;
+    let (result, out) = env.capture_output(|env| eval_in("(inc :a)", env));
+    assert!(result.is_err());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "(inc :a)\n!! expected number, got keyword\n"
+    );
+}
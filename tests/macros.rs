@@ -0,0 +1,65 @@
+mod common;
+
+use common::{error, show};
+use warp::EvalError;
+
+const MACROS: &str = "(defmacro unless [c a b] (list 'if c b a))
+                      (defmacro my-not [x] (list 'unless x false true))";
+
+#[test]
+fn macroexpand_1_expands_one_step() {
+    assert_eq!(
+        show(&format!("{} (macroexpand-1 '(my-not y))", MACROS)),
+        "(unless y false true)"
+    );
+}
+
+#[test]
+fn macroexpand_expands_fully() {
+    assert_eq!(
+        show(&format!("{} (macroexpand '(my-not y))", MACROS)),
+        "(if y true false)"
+    );
+}
+
+#[test]
+fn non_macro_forms_are_unchanged() {
+    assert_eq!(
+        show(&format!("{} (macroexpand-1 '(+ 1 2))", MACROS)),
+        "(+ 1 2)"
+    );
+    assert_eq!(show(&format!("{} (macroexpand :k)", MACROS)), ":k");
+}
+
+#[test]
+fn syntax_quote_unquotes_and_splices() {
+    assert_eq!(show("(def b 2) (def c [3 4]) `(a ~b ~@c)"), "(a 2 3 4)");
+    assert_eq!(show("(def c '(3 4)) `[~@c 5 ~(inc 5)]"), "[3 4 5 6]");
+    assert_eq!(show("`{:k ~(+ 1 2)}"), "{:k 3}");
+    assert_eq!(show("`(a (b ~@[]))"), "(a (b))");
+}
+
+#[test]
+fn syntax_quote_gives_each_auto_gensym_one_name() {
+    assert_eq!(show("(apply = `[x# x#])"), "true");
+    assert_eq!(show("(= `x# `x#)"), "false");
+}
+
+#[test]
+fn splicing_needs_a_collection_around_it() {
+    assert!(matches!(error("`~@[1 2]"), EvalError::Malformed(_)));
+}
+
+#[test]
+fn macros_can_be_written_with_syntax_quote() {
+    let src = "(defmacro unless [c & body] `(if ~c nil (do ~@body)))
+               (unless false 1 2)";
+    assert_eq!(show(src), "2");
+    assert_eq!(
+        show(
+            "(defmacro unless [c & body] `(if ~c nil (do ~@body)))
+              (macroexpand '(unless x 1 2))"
+        ),
+        "(if x nil (do 1 2))"
+    );
+}
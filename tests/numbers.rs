@@ -0,0 +1,193 @@
+mod common;
+
+use common::show;
+use warp::{Atom, EvalError, LexError, AST};
+
+#[test]
+fn radix_literals_read_as_ints() {
+    assert_eq!(show("0x1F"), "31");
+    assert_eq!(show("0o17"), "15");
+    assert_eq!(show("0b1010"), "10");
+    assert_eq!(show("2r1010"), "10");
+    assert_eq!(show("36rZZ"), "1295");
+    assert_eq!(show("-0x10"), "-16");
+}
+
+#[test]
+fn bad_radix_digits_are_an_error() {
+    for src in ["0b102", "8r9", "0xG"] {
+        match AST::tokenize(src.to_string()) {
+            Err(LexError::InvalidNumber { token, .. }) => assert_eq!(token, src),
+            other => panic!("{} gave {:?}", src, other),
+        }
+    }
+}
+
+#[test]
+fn dividing_ints_is_exact() {
+    assert_eq!(show("(/ 1 2)"), "1/2");
+    assert_eq!(show("(/ 6 3)"), "2");
+    assert_eq!(show("(/ 4 6)"), "2/3");
+    assert_eq!(show("(/ 1 -2)"), "-1/2");
+    assert_eq!(show("(/ 3)"), "1/3");
+    assert_eq!(show("(/ 12 2 3)"), "2");
+    assert_eq!(show("(= (/ 1 2) 1/2)"), "true");
+}
+
+#[test]
+fn arithmetic_on_ratios_stays_exact() {
+    assert_eq!(show("(+ 1/2 1/3)"), "5/6");
+    assert_eq!(show("(+ 1/2 1/2)"), "1");
+    assert_eq!(show("(- 1/2 1/3)"), "1/6");
+    assert_eq!(show("(- 1/2)"), "-1/2");
+    assert_eq!(show("(* 2 1/2)"), "1");
+    assert_eq!(show("(* 2/3 3/4)"), "1/2");
+    assert_eq!(show("(/ 1/2 1/4)"), "2");
+    assert_eq!(show("(inc 1/2)"), "3/2");
+    assert_eq!(show("(dec 1/2)"), "-1/2");
+}
+
+#[test]
+fn floats_make_ratio_arithmetic_inexact() {
+    assert_eq!(show("(+ 1/2 0.25)"), "0.75");
+    assert_eq!(show("(/ 1.0 2)"), "0.5");
+    assert_eq!(show("(* 1/2 1.0)"), "0.5");
+}
+
+#[test]
+fn dividing_by_exact_zero_is_an_error() {
+    assert_eq!(common::error("(/ 1 0)"), EvalError::DivideByZero);
+    assert_eq!(common::error("(/ 1/2 0)"), EvalError::DivideByZero);
+    assert_eq!(common::error("(/ 0)"), EvalError::DivideByZero);
+}
+
+#[test]
+fn ratio_arithmetic_that_doesnt_fit_overflows() {
+    assert_eq!(
+        common::error("(+ 1/9223372036854775807 1/9223372036854775806)"),
+        EvalError::Overflow
+    );
+}
+
+#[test]
+fn ratio_literals_read_in_lowest_terms() {
+    assert_eq!(Atom::infer("3/4", true), Some(Atom::Ratio(3, 4)));
+    assert_eq!(Atom::infer("-6/8", true), Some(Atom::Ratio(-3, 4)));
+    assert_eq!(Atom::infer("4/2", true), Some(Atom::Int(2)));
+    assert_eq!(show("3/4"), "3/4");
+}
+
+#[test]
+fn bad_ratio_literals_are_read_errors() {
+    for src in ["1/0", "1/2/3", "1/-2", "1/"] {
+        match AST::tokenize(src.to_string()) {
+            Err(LexError::InvalidNumber { token, .. }) => assert_eq!(token, src),
+            other => panic!("{} gave {:?}", src, other),
+        }
+    }
+}
+
+#[test]
+fn numeric_predicates() {
+    let cases = [
+        ("(even? 2)", "true"),
+        ("(even? 3)", "false"),
+        ("(odd? 3)", "true"),
+        ("(odd? -3)", "true"),
+        ("(zero? 0)", "true"),
+        ("(zero? 0.0)", "true"),
+        ("(zero? 1)", "false"),
+        ("(pos? 1/2)", "true"),
+        ("(pos? 0)", "false"),
+        ("(neg? -0.5)", "true"),
+        ("(neg? 0)", "false"),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(show(src), expected, "{}", src);
+    }
+}
+
+#[test]
+fn numeric_predicates_reject_non_numbers() {
+    for name in ["even?", "odd?", "zero?", "pos?", "neg?"] {
+        let src = format!("({} \"1\")", name);
+        assert!(
+            matches!(common::error(&src), EvalError::Type { .. }),
+            "{}",
+            src
+        );
+    }
+}
+
+#[test]
+fn sqrt_and_pow_promote_to_float() {
+    assert_eq!(show("(sqrt 16)"), "4.0");
+    assert_eq!(show("(sqrt 2.25)"), "1.5");
+    assert_eq!(show("(pow 2 10)"), "1024.0");
+    assert_eq!(show("(pow 4 0.5)"), "2.0");
+    assert_eq!(show("(pow 2 -1)"), "0.5");
+}
+
+#[test]
+fn math_domain_errors_are_nan() {
+    for src in ["(sqrt -1)", "(pow -8 1/3)"] {
+        match common::eval_str(src) {
+            Ok(Atom::Float(n)) => assert!(n.is_nan(), "{} gave {}", src, n),
+            other => panic!("{} gave {:?}", src, other),
+        }
+    }
+}
+
+#[test]
+fn abs_keeps_the_kind_of_number() {
+    assert_eq!(show("(abs -3)"), "3");
+    assert_eq!(show("(abs -1.5)"), "1.5");
+    assert_eq!(show("(abs -1/2)"), "1/2");
+    assert_eq!(
+        common::error("(abs -9223372036854775808)"),
+        EvalError::Overflow
+    );
+}
+
+#[test]
+fn floor_ceil_and_round() {
+    assert_eq!(show("(floor 1.5)"), "1.0");
+    assert_eq!(show("(ceil 1.5)"), "2.0");
+    assert_eq!(show("(floor 3)"), "3");
+    assert_eq!(show("(round 2.5)"), "3");
+    assert_eq!(show("(round -2.5)"), "-3");
+    assert_eq!(show("(< 3.14 pi 3.15)"), "true");
+}
+
+#[test]
+fn seeding_makes_rand_deterministic() {
+    let src = "(set-seed 42) [(rand) (rand-int 100) (rand-int 100) (rand 10)]";
+    assert_eq!(show(src), show(src));
+    assert_ne!(
+        show("(set-seed 1) [(rand) (rand)]"),
+        show("(set-seed 2) [(rand) (rand)]")
+    );
+}
+
+#[test]
+fn rand_stays_in_range() {
+    assert_eq!(
+        show("(set-seed 7) (every? #(if (>= % 0) (< % 1) false) (repeatedly 200 rand))"),
+        "true"
+    );
+    assert_eq!(
+        show("(set-seed 7) (every? #(if (>= % 0) (< % 5) false) (repeatedly 200 #(rand-int 5)))"),
+        "true"
+    );
+}
+
+#[test]
+fn rand_int_needs_a_positive_bound() {
+    assert!(matches!(
+        common::error("(rand-int 0)"),
+        EvalError::Type {
+            expected: "positive int",
+            ..
+        }
+    ));
+}
@@ -0,0 +1,52 @@
+// the round trip saves a regex too
+#![cfg(all(feature = "persist", feature = "regex"))]
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use common::eval_in;
+use warp::ENV;
+
+fn temp_file(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("warp-{}-{}.json", name, std::process::id()))
+}
+
+#[test]
+fn saved_bindings_round_trip() {
+    let path = temp_file("round-trip");
+    let mut saving = ENV::new();
+    let src = format!(
+        "(def m {{:a 1 \"b\" [2.5 nil true]}})
+         (def s #{{:x 'y \"z\"}})
+         (def r 3/4)
+         (def text \"tab\\t quote\\\" slash\\\\ newline\\n é\")
+         (def re #\"(\\d+)-(\\w+)\")
+         (def f (fn [x] x))
+         (save-env {:?})",
+        path.to_str().unwrap()
+    );
+    let (result, out) = saving.capture_output(|env| eval_in(&src, env));
+    result.unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "WARNING: not saving f, it holds a function, which can't be saved\n"
+    );
+
+    let mut loading = ENV::new();
+    assert_eq!(loading.load(&path).unwrap(), 5);
+    fs::remove_file(&path).unwrap();
+    for name in ["m", "s", "r", "text"] {
+        assert_eq!(loading.lookup(name), saving.lookup(name), "{}", name);
+    }
+    assert_eq!(loading.lookup("f"), None);
+    let found = eval_in("(re-find re \"12-ab\")", &mut loading).unwrap();
+    assert_eq!(found.readable().to_string(), "[\"12-ab\" \"12\" \"ab\"]");
+}
+
+#[test]
+fn loading_a_missing_file_fails() {
+    assert!(ENV::new().load(temp_file("missing")).is_err());
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::eval_str;
+use warp::PrettyPrinter;
+
+fn pretty(src: &str, color: bool) -> String {
+    PrettyPrinter::new(color).print(&eval_str(src).unwrap())
+}
+
+#[test]
+fn color_codes_appear_when_color_is_on() {
+    assert_eq!(pretty(":k", true), "\x1b[35m:k\x1b[0m");
+    assert_eq!(pretty("\"s\"", true), "\x1b[32ms\x1b[0m");
+    assert_eq!(pretty("1/2", true), "\x1b[36m1/2\x1b[0m");
+    assert_eq!(pretty("nil", true), "\x1b[33mnil\x1b[0m");
+    // only the atoms inside a collection are colored
+    assert_eq!(
+        pretty("'[1 foo {:a true}]", true),
+        "[\x1b[36m1\x1b[0m foo {\x1b[35m:a\x1b[0m \x1b[33mtrue\x1b[0m}]"
+    );
+}
+
+#[test]
+fn no_color_codes_when_color_is_off() {
+    for src in [
+        ":k",
+        "\"s\"",
+        "1.5",
+        "'(1 :a \"b\" nil)",
+        "{:a #{1}}",
+        "(atom [false])",
+    ] {
+        let printed = pretty(src, false);
+        assert!(!printed.contains('\x1b'), "{} printed {:?}", src, printed);
+        assert_eq!(printed, eval_str(src).unwrap().to_string());
+    }
+}
+
+#[test]
+fn colored_text_is_display_inside_the_codes() {
+    let colored = pretty("[:a \"b\" 3 (atom nil)]", true);
+    let mut plain = String::new();
+    let mut rest = colored.as_str();
+    while let Some(start) = rest.find('\x1b') {
+        plain.push_str(&rest[..start]);
+        rest = &rest[start + rest[start..].find('m').unwrap() + 1..];
+    }
+    plain.push_str(rest);
+    assert_eq!(plain, "[:a b 3 #<atom nil>]");
+}
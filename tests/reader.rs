@@ -0,0 +1,459 @@
+mod common;
+
+use warp::{Atom, Dispatch, Form, LexError, ParseError, ParseErrorKind, Reader, AST};
+
+#[test]
+fn unterminated_string_points_at_its_opening_quote() {
+    let src = "(def a 1)\n(def b 2)\n(def c \"abc\n\n(def d 4)";
+    assert_eq!(
+        AST::tokenize(src.to_string()).unwrap_err(),
+        LexError::UnterminatedString { line: 3, col: 8 }
+    );
+}
+
+#[test]
+fn literal_duplicates_in_a_set_are_an_error() {
+    let tokens = AST::tokenize("#{1 2 1}".to_string()).unwrap();
+    let err = AST::read(&tokens).unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::DuplicateKey("1".to_string()));
+    assert_eq!(err.token, "#{");
+}
+
+fn token_values(src: &str) -> Vec<String> {
+    AST::tokenize(src.to_string())
+        .unwrap()
+        .into_iter()
+        .map(|token| token.value)
+        .collect()
+}
+
+#[test]
+fn punctuation_stays_inside_symbol_names() {
+    for name in [
+        "empty?",
+        "swap!",
+        "*ns*",
+        "map.entry",
+        "ends.",
+        "a->b",
+        "<=",
+        "x'",
+        "_",
+        "+",
+    ] {
+        assert_eq!(
+            token_values(&format!("({} 1)", name)),
+            ["(", name, "1", ")"]
+        );
+    }
+}
+
+#[test]
+fn symbols_like_inf_are_not_floats() {
+    assert_eq!(
+        common::show("(quote [inf NaN infinity])"),
+        "[inf NaN infinity]"
+    );
+}
+
+#[test]
+fn nested_fn_literals_are_an_error() {
+    let tokens = AST::tokenize("#(+ % #(inc %))".to_string()).unwrap();
+    assert_eq!(
+        AST::read(&tokens).unwrap_err().kind,
+        ParseErrorKind::NestedFnLiteral
+    );
+}
+
+fn parse_error(src: &str) -> ParseError {
+    AST::read(&AST::tokenize(src.to_string()).unwrap()).unwrap_err()
+}
+
+#[test]
+fn parse_errors_name_the_offending_token() {
+    let err = parse_error("(+ 1 2))");
+    assert_eq!((err.token.as_str(), err.line, err.col), (")", 1, 8));
+    assert_eq!(err.to_string(), "1:8: unexpected ')'");
+
+    let err = parse_error("(list\n  1 2]");
+    assert_eq!((err.token.as_str(), err.line, err.col), ("]", 2, 6));
+    assert!(err.to_string().contains("found ']'"), "{}", err);
+}
+
+fn tree(src: &str) -> Vec<Form> {
+    AST::read_tree(&AST::tokenize(src.to_string()).unwrap()).unwrap()
+}
+
+/// A string literal is one token that becomes an atom, not a form.
+#[test]
+fn strings_read_as_atoms() {
+    assert_eq!(tree("\"abc\""), [Form::Atom(Atom::string("abc"))]);
+    let ast = AST::read(&AST::tokenize("\"a (b)\"".to_string()).unwrap()).unwrap();
+    assert!(ast.items.is_empty());
+}
+
+/// `'` is a reader macro wrapping the next form in a quote, not a list type.
+#[test]
+fn quote_wraps_the_next_form() {
+    assert_eq!(
+        tree("'(1 2)"),
+        [Form::Quote(Box::new(Form::List(vec![
+            Form::Atom(Atom::Int(1)),
+            Form::Atom(Atom::Int(2))
+        ])))]
+    );
+    assert_eq!(common::show("'(1 2)"), "(1 2)");
+}
+
+#[test]
+fn infer_reads_each_kind_of_atom() {
+    let cases = [
+        ("42", Atom::Int(42)),
+        ("-7", Atom::Int(-7)),
+        ("2.5", Atom::Float(2.5)),
+        ("-.5", Atom::Float(-0.5)),
+        ("true", Atom::Boolean(true)),
+        ("false", Atom::Boolean(false)),
+        ("nil", Atom::Nil),
+        (":key", Atom::Keyword("key".to_string())),
+        ("name", Atom::Symbol("name".to_string())),
+        ("\"a\\nb\"", Atom::string("a\nb")),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(Atom::infer(src, true), Some(expected), "{}", src);
+    }
+    assert_eq!(Atom::infer("1abc", true), None);
+}
+
+#[test]
+fn hash_makes_dispatch_tokens() {
+    let tokens = AST::tokenize("#{1} #(inc %) #_ x #\"a+\" #inst \"t\"".to_string()).unwrap();
+    let dispatches: Vec<(&str, Option<Dispatch>)> = tokens
+        .iter()
+        .map(|token| (token.value.as_str(), token.dispatch()))
+        .filter(|(_, dispatch)| dispatch.is_some())
+        .collect();
+    assert_eq!(
+        dispatches,
+        [
+            ("#{", Some(Dispatch::Set)),
+            ("#(", Some(Dispatch::Fn)),
+            ("#_", Some(Dispatch::Discard)),
+            ("#\"a+\"", Some(Dispatch::Regex)),
+            ("#inst", Some(Dispatch::Tag)),
+        ]
+    );
+}
+
+#[test]
+fn discard_skips_the_next_form() {
+    assert_eq!(common::show("[1 #_ 2 #_(3 4) 5]"), "[1 5]");
+}
+
+#[test]
+fn unknown_dispatch_is_a_lex_error() {
+    assert!(matches!(
+        AST::tokenize("#%".to_string()),
+        Err(LexError::UnknownDispatch {
+            found: Some('%'),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn tokens_that_start_like_numbers_must_be_numbers() {
+    match AST::tokenize("(+ 1abc 2)".to_string()) {
+        Err(LexError::InvalidNumber { token, line, col }) => {
+            assert_eq!((token.as_str(), line, col), ("1abc", 1, 4))
+        }
+        other => panic!("expected an invalid number, got {:?}", other),
+    }
+    assert!(AST::tokenize("-2x".to_string()).is_err());
+}
+
+#[test]
+fn digits_later_in_a_symbol_are_fine() {
+    assert_eq!(
+        Atom::infer("a1", true),
+        Some(Atom::Symbol("a1".to_string()))
+    );
+    assert_eq!(common::show("(quote [a1 x2y -a])"), "[a1 x2y -a]");
+}
+
+#[test]
+fn read_tree_nests_owned_forms() {
+    let symbol = |name: &str| Form::Atom(Atom::Symbol(name.to_string()));
+    assert_eq!(
+        tree("(a (b))"),
+        [Form::List(vec![symbol("a"), Form::List(vec![symbol("b")])])]
+    );
+    assert_eq!(
+        tree("[x {:k #{1}}] y"),
+        [
+            Form::Vector(vec![
+                symbol("x"),
+                Form::Map(vec![(
+                    Form::Atom(Atom::Keyword("k".to_string())),
+                    Form::Set(vec![Form::Atom(Atom::Int(1))])
+                )])
+            ]),
+            symbol("y")
+        ]
+    );
+}
+
+#[test]
+fn infer_can_leave_literal_names_as_symbols() {
+    let cases = [
+        ("true", Atom::Boolean(true)),
+        ("false", Atom::Boolean(false)),
+        ("nil", Atom::Nil),
+    ];
+    for (src, literal) in cases {
+        assert_eq!(Atom::infer(src, true), Some(literal), "{}", src);
+        assert_eq!(
+            Atom::infer(src, false),
+            Some(Atom::Symbol(src.to_string())),
+            "{}",
+            src
+        );
+    }
+    // everything else reads the same either way
+    for src in ["12", ":k", "name", "\"s\""] {
+        assert_eq!(Atom::infer(src, false), Atom::infer(src, true), "{}", src);
+    }
+}
+
+fn open_forms(src: &str) -> Result<usize, ParseError> {
+    AST::open_forms(&AST::tokenize(src.to_string()).unwrap())
+}
+
+#[test]
+fn open_forms_of_balanced_input_is_zero() {
+    for src in [
+        "",
+        "(+ 1 2)",
+        "[1 {:a #{2}}] (f)",
+        "'x",
+        "#(inc %)",
+        "\"(\"",
+    ] {
+        assert_eq!(open_forms(src), Ok(0), "{}", src);
+    }
+}
+
+#[test]
+fn open_forms_counts_what_is_still_open() {
+    assert_eq!(open_forms("(+ 1"), Ok(1));
+    assert_eq!(open_forms("(let [x {:a"), Ok(3));
+    assert_eq!(open_forms("(f) (g"), Ok(1));
+    // a quote is waiting for the form it applies to
+    assert_eq!(open_forms("'"), Ok(1));
+}
+
+#[test]
+fn open_forms_rejects_extra_or_mismatched_closers() {
+    let err = open_forms("(+ 1 2))").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::UnexpectedClose);
+    assert_eq!((err.line, err.col), (1, 8));
+
+    let err = open_forms("[1 2)").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::Mismatched { expected: "]" });
+    assert_eq!(
+        open_forms("')").unwrap_err().kind,
+        ParseErrorKind::UnexpectedClose
+    );
+}
+
+fn upper_reader() -> Reader {
+    let mut reader = Reader::new();
+    reader.register_tag("upper", |form| match form {
+        Atom::String(s) => Ok(Atom::string(s.to_uppercase())),
+        other => Err(format!("#upper takes a string, got {}", other.readable())),
+    });
+    reader
+}
+
+#[test]
+fn registered_tags_transform_the_next_form() {
+    let tokens = AST::tokenize("#upper \"hi\"".to_string()).unwrap();
+    let ast = upper_reader().read(&tokens).unwrap();
+    assert_eq!(
+        warp::eval(&ast, &mut warp::ENV::new()).unwrap(),
+        Atom::string("HI")
+    );
+    let tokens = AST::tokenize("(count [#upper \"a\" 1])".to_string()).unwrap();
+    let ast = upper_reader().read(&tokens).unwrap();
+    assert_eq!(
+        warp::eval(&ast, &mut warp::ENV::new()).unwrap(),
+        Atom::Int(2)
+    );
+}
+
+#[test]
+fn tag_handler_errors_and_unknown_tags_are_parse_errors() {
+    let tokens = AST::tokenize("#upper 1".to_string()).unwrap();
+    let err = upper_reader().read(&tokens).unwrap_err();
+    assert_eq!(
+        err.kind,
+        ParseErrorKind::InvalidTagged("#upper takes a string, got 1".to_string())
+    );
+
+    let tokens = AST::tokenize("#lower \"HI\"".to_string()).unwrap();
+    let err = upper_reader().read(&tokens).unwrap_err();
+    assert_eq!(
+        (err.kind, err.token.as_str()),
+        (ParseErrorKind::UnknownTag, "#lower")
+    );
+}
+
+#[test]
+fn positions_count_characters_not_bytes() {
+    let tokens = AST::tokenize("(déf naïve \"日本\" λ)\n  (ß)".to_string()).unwrap();
+    let positions: Vec<(&str, usize, usize)> = tokens
+        .iter()
+        .map(|token| (token.value.as_str(), token.line, token.col))
+        .collect();
+    assert_eq!(
+        positions,
+        [
+            ("(", 1, 1),
+            ("déf", 1, 2),
+            ("naïve", 1, 6),
+            ("\"日本\"", 1, 12),
+            ("λ", 1, 17),
+            (")", 1, 18),
+            ("(", 2, 3),
+            ("ß", 2, 4),
+            (")", 2, 5)
+        ]
+    );
+}
+
+#[test]
+fn error_positions_after_multibyte_characters() {
+    match AST::tokenize("(é \"ü".to_string()) {
+        Err(LexError::UnterminatedString { line, col }) => assert_eq!((line, col), (1, 4)),
+        other => panic!("expected an unterminated string, got {:?}", other),
+    }
+    let err = parse_error("[日本 ü)");
+    assert_eq!((err.line, err.col), (1, 6));
+}
+
+#[test]
+fn unicode_symbols_evaluate() {
+    assert_eq!(common::show("(def π² 9.87) (let [λ 2] [π² λ])"), "[9.87 2]");
+}
+
+#[test]
+fn infer_tries_parsers_in_order() {
+    let cases = [
+        ("1/2", Atom::Ratio(1, 2)),
+        ("-3/4", Atom::Ratio(-3, 4)),
+        ("1.5", Atom::Float(1.5)),
+        ("1e3", Atom::Float(1000.0)),
+        ("10", Atom::Int(10)),
+        ("0x10", Atom::Int(16)),
+        ("true", Atom::Boolean(true)),
+        ("\\a", Atom::Char('a')),
+        (":a/b", Atom::Keyword("a/b".to_string())),
+        ("a/b", Atom::Symbol("a/b".to_string())),
+        ("inf", Atom::Symbol("inf".to_string())),
+        ("truthy", Atom::Symbol("truthy".to_string())),
+        ("+", Atom::Symbol("+".to_string())),
+        ("-", Atom::Symbol("-".to_string())),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(Atom::infer(src, true), Some(expected), "{}", src);
+    }
+}
+
+#[test]
+fn infer_rejects_malformed_literals() {
+    for src in ["1abc", "1/0", "1/2/3", "\\foo", "\\u12", "\"", "\"abc"] {
+        assert_eq!(Atom::infer(src, true), None, "{}", src);
+    }
+}
+
+#[test]
+fn character_literals() {
+    let cases = [
+        ("\\a", 'a'),
+        ("\\λ", 'λ'),
+        ("\\newline", '\n'),
+        ("\\space", ' '),
+        ("\\tab", '\t'),
+        ("\\u00e9", 'é'),
+        ("\\u", 'u'),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(
+            Atom::infer(src, true),
+            Some(Atom::Char(expected)),
+            "{}",
+            src
+        );
+    }
+}
+
+#[test]
+fn characters_can_be_delimiters() {
+    assert_eq!(common::show("[\\( \\) \\\" \\a]"), "[\\( \\) \\\" \\a]");
+    assert_eq!(common::show("(count [\\[ \\space])"), "2");
+    assert_eq!(common::show("[\\newline \\ ]"), "[\\newline \\space]");
+    assert_eq!(common::show("(type \\x)"), ":char");
+    assert_eq!(common::output("(print \\a \\b)"), "a b");
+}
+
+#[test]
+fn unknown_character_names_are_lex_errors() {
+    match AST::tokenize("(list \\foo)".to_string()) {
+        Err(LexError::InvalidCharacter { token, line, col }) => {
+            assert_eq!((token.as_str(), line, col), ("\\foo", 1, 7))
+        }
+        other => panic!("expected an invalid character, got {:?}", other),
+    }
+}
+
+fn read_for(platform: &str, src: &str) -> Result<Atom, ParseError> {
+    let tokens = AST::tokenize(src.to_string()).unwrap();
+    let mut reader = Reader::new();
+    reader.set_platform(platform);
+    let ast = reader.read(&tokens)?;
+    Ok(warp::eval(&ast, &mut warp::ENV::new()).unwrap())
+}
+
+#[test]
+fn reader_conditionals_keep_the_platforms_branch() {
+    assert_eq!(read_for("warp", "#?(:warp 1 :default 2)"), Ok(Atom::Int(1)));
+    assert_eq!(read_for("host", "#?(:warp 1 :host 3)"), Ok(Atom::Int(3)));
+    assert_eq!(read_for("host", "#?(:warp 1 :default 2)"), Ok(Atom::Int(2)));
+    // the first matching branch wins, default or not
+    assert_eq!(read_for("warp", "#?(:default 2 :warp 1)"), Ok(Atom::Int(2)));
+    let tokens = AST::tokenize("#?(:clj 0 :warp 1)".to_string()).unwrap();
+    assert_eq!(AST::read(&tokens).unwrap().to_string(), "1\n");
+}
+
+#[test]
+fn reader_conditionals_without_a_match_read_as_nothing() {
+    assert_eq!(
+        read_for("warp", "[1 #?(:clj 2) 3]"),
+        Ok(Atom::vector(vec![Atom::Int(1), Atom::Int(3)]))
+    );
+    assert_eq!(read_for("warp", "(count [#?(:clj 2)])"), Ok(Atom::Int(0)));
+}
+
+#[test]
+fn malformed_reader_conditionals_are_parse_errors() {
+    for src in ["#?(:warp)", "#?(warp 1)"] {
+        let err = read_for("warp", src).unwrap_err();
+        assert!(
+            matches!(err.kind, ParseErrorKind::InvalidConditional(_)),
+            "{}: {:?}",
+            src,
+            err
+        );
+        assert_eq!(err.token, "#?(");
+    }
+}
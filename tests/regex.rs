@@ -0,0 +1,53 @@
+#![cfg(feature = "regex")]
+
+mod common;
+
+use common::{eval_in, show};
+use warp::{Atom, ParseErrorKind, AST, ENV};
+
+#[test]
+fn re_find_matching_and_not_matching() {
+    assert_eq!(show("(re-find #\"\\d+\" \"abc 123 def\")"), "\"123\"");
+    assert_eq!(show("(re-find #\"\\d+\" \"no digits\")"), "nil");
+    assert_eq!(
+        show("(re-find #\"(\\w+)@(\\w+)\" \"mail bob@host now\")"),
+        "[\"bob@host\" \"bob\" \"host\"]"
+    );
+}
+
+#[test]
+fn re_matches_needs_the_whole_string() {
+    assert_eq!(show("(re-matches #\"\\d+\" \"123\")"), "\"123\"");
+    assert_eq!(show("(re-matches #\"\\d+\" \"123abc\")"), "nil");
+    assert_eq!(show("(re-matches #\"a|ab\" \"ab\")"), "\"ab\"");
+    assert_eq!(show("(re-matches #\"(a)|(b)\" \"b\")"), "[\"b\" nil \"b\"]");
+}
+
+#[test]
+fn re_pattern_compiles_strings() {
+    assert_eq!(show("(re-find (re-pattern \"b+\") \"abbbc\")"), "\"bbb\"");
+}
+
+#[test]
+fn invalid_patterns_fail_at_read_time() {
+    let tokens = AST::tokenize("#\"(ab\"".to_string()).unwrap();
+    let err = AST::read(&tokens).unwrap_err();
+    assert!(matches!(err.kind, ParseErrorKind::InvalidRegex(_)));
+}
+
+#[test]
+fn long_inputs_and_nested_repeats_are_fine() {
+    let mut env = ENV::new();
+    env.define("long", Atom::string("a".repeat(50_000)));
+    env.define("as", Atom::string("a".repeat(30)));
+    let found = eval_in("(count (re-find #\"a+\" long))", &mut env).unwrap();
+    assert_eq!(found, Atom::Int(50_000));
+    let found = eval_in("(re-find #\"(a*)*b\" as)", &mut env).unwrap();
+    assert_eq!(found, Atom::Nil);
+}
+
+#[test]
+fn word_classes_and_boundaries_agree_on_unicode() {
+    assert_eq!(show("(re-find #\"\\w+\" \"été!\")"), "\"été\"");
+    assert_eq!(show("(re-find #\"\\bé\\w*\\b\" \"un été\")"), "\"été\"");
+}
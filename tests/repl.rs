@@ -0,0 +1,106 @@
+use warp::Repl;
+
+/// Runs `repl` over `input`, returning everything it wrote and the status
+/// it ended with.
+fn session(repl: &mut Repl, input: &str) -> (String, i32) {
+    let mut output = Vec::new();
+    let status = repl.run(&mut input.as_bytes(), &mut output).unwrap();
+    (String::from_utf8(output).unwrap(), status)
+}
+
+#[test]
+fn uses_a_custom_prompt_and_banner() {
+    let mut repl = Repl::new();
+    repl.prompt = "> ".to_string();
+    repl.banner = Some("hello".to_string());
+    let (output, _) = session(&mut repl, "(+ 1 2)\n");
+    assert_eq!(output, "hello\n> 3\n> \n");
+}
+
+#[test]
+fn default_prompt_and_banner() {
+    let (output, _) = session(&mut Repl::new(), "");
+    assert_eq!(
+        output,
+        format!("Warp {}\nwarp=> \n", env!("CARGO_PKG_VERSION"))
+    );
+}
+
+#[test]
+fn banner_can_be_turned_off() {
+    let mut repl = Repl::new();
+    repl.banner = None;
+    assert_eq!(session(&mut repl, "1\n").0, "warp=> 1\nwarp=> \n");
+}
+
+#[test]
+fn quit_ends_the_session_cleanly() {
+    let mut repl = Repl::new();
+    repl.banner = None;
+    assert_eq!(
+        session(&mut repl, "1\n:quit\n2\n"),
+        ("warp=> 1\nwarp=> ".to_string(), 0)
+    );
+}
+
+#[test]
+fn exit_ends_the_session_with_its_code() {
+    let mut repl = Repl::new();
+    repl.banner = None;
+    assert_eq!(
+        session(&mut repl, "(exit 4)\n2\n"),
+        ("warp=> ".to_string(), 4)
+    );
+}
+
+#[test]
+fn an_open_form_continues_on_the_next_line() {
+    let mut repl = Repl::new();
+    repl.banner = None;
+    assert_eq!(
+        session(&mut repl, "(+ 1\n2)\n").0,
+        format!("warp=> {}3\nwarp=> \n", repl.continuation)
+    );
+}
+
+#[test]
+fn strings_can_span_lines() {
+    let mut repl = Repl::new();
+    repl.banner = None;
+    repl.continuation = "... ".to_string();
+    assert_eq!(
+        session(&mut repl, "(count \"a\nb\")\n(count\n[1\n2])\n").0,
+        "warp=> ... 3\nwarp=> ... ... 2\nwarp=> \n"
+    );
+}
+
+#[test]
+fn too_many_closers_is_an_error_not_a_continuation() {
+    let mut repl = Repl::new();
+    repl.banner = None;
+    let (output, _) = session(&mut repl, "(+ 1 2))\n4\n");
+    assert!(output.starts_with("warp=> error: "), "{}", output);
+    assert!(output.ends_with("warp=> 4\nwarp=> \n"), "{}", output);
+}
+
+#[test]
+fn defining_a_name_prints_it_as_a_var() {
+    let mut repl = Repl::new();
+    repl.banner = None;
+    assert_eq!(
+        session(&mut repl, "(def x 1)\nx\n'x\n").0,
+        "warp=> #'x\nwarp=> 1\nwarp=> x\nwarp=> \n"
+    );
+}
+
+#[test]
+fn results_are_colored_only_when_color_is_on() {
+    let mut repl = Repl::new();
+    repl.banner = None;
+    assert_eq!(session(&mut repl, ":k\n").0, "warp=> :k\nwarp=> \n");
+    repl.color = true;
+    assert_eq!(
+        session(&mut repl, ":k\n").0,
+        "warp=> \x1b[35m:k\x1b[0m\nwarp=> \n"
+    );
+}
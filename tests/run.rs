@@ -0,0 +1,51 @@
+use std::fs;
+use std::path::PathBuf;
+
+use warp::{run_file, Atom, EvalError, RunError, ENV};
+
+/// Writes `src` to a file in the temp directory, named after the test so
+/// tests running at once don't share one.
+fn script(name: &str, src: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("warp-{}-{}.clj", std::process::id(), name));
+    fs::write(&path, src).unwrap();
+    path
+}
+
+#[test]
+fn run_file_returns_the_last_value_and_the_output() {
+    let path = script(
+        "prints",
+        "(println \"start\")\n(defn sq [x] (* x x))\n(print (sq 3))\n(+ (sq 4) 1)\n",
+    );
+    let mut env = ENV::new();
+    let (value, output) = run_file(&path, &mut env).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(value, Atom::Int(17));
+    assert_eq!(output, "start\n9");
+    // what the file defined stays defined
+    assert!(env.lookup("sq").is_some());
+}
+
+#[test]
+fn run_file_keeps_definitions_made_before_a_failure() {
+    let path = script(
+        "fails",
+        "(def x 1)\n(print \"partial\")\n(inc :k)\n(def y 2)",
+    );
+    let mut env = ENV::new();
+    let err = run_file(&path, &mut env).unwrap_err();
+    fs::remove_file(&path).unwrap();
+    assert!(
+        matches!(err, RunError::Eval(EvalError::Type { .. })),
+        "{}",
+        err
+    );
+    assert!(env.lookup("x").is_some());
+    assert!(env.lookup("y").is_none());
+}
+
+#[test]
+fn run_file_of_a_missing_file_is_an_io_error() {
+    let err = run_file("/nonexistent/warp-script.clj", &mut ENV::new()).unwrap_err();
+    assert!(matches!(err, RunError::Io(_)), "{}", err);
+}
@@ -0,0 +1,263 @@
+mod common;
+
+use common::{error, eval_in, output, show};
+use warp::{Atom, EvalError, ENV};
+
+#[test]
+fn for_returns_a_vector() {
+    assert_eq!(show("(for [x [1 2 3]] (* x x))"), "[1 4 9]");
+}
+
+#[test]
+fn for_with_nested_bindings_is_the_cartesian_product() {
+    assert_eq!(
+        show("(for [x [1 2] y [:a :b]] [x y])"),
+        "[[1 :a] [1 :b] [2 :a] [2 :b]]"
+    );
+}
+
+#[test]
+fn later_bindings_see_earlier_ones() {
+    assert_eq!(show("(for [xs [[1 2] [3]] x xs] x)"), "[1 2 3]");
+}
+
+#[test]
+fn doseq_runs_the_body_for_effects() {
+    assert_eq!(
+        output("(doseq [x [1 2] y [3 4]] (print x y \"\"))"),
+        "1 3 1 4 2 3 2 4 "
+    );
+    assert_eq!(show("(doseq [x [1 2]] x)"), "nil");
+}
+
+#[test]
+fn defonce_skips_the_value_when_already_bound() {
+    let src = "(defonce x (do (println \"init\") 1))
+               (defonce x (do (println \"again\") 2))
+               (println x)";
+    assert_eq!(output(src), "init\n1\n");
+}
+
+#[test]
+fn redefining_a_symbol_warns() {
+    assert_eq!(
+        output("(def x 1) (def x 2)"),
+        "WARNING: x is already defined, redefining it\n"
+    );
+    assert_eq!(output("(def x 1) (defonce x 2)"), "");
+}
+
+#[test]
+fn defn_docstrings_dont_change_the_arity() {
+    assert_eq!(
+        output("(defn f \"the doc\" [x] x) (doc f)"),
+        "-------------------------\nf\n  the doc\n"
+    );
+    assert_eq!(show("(defn f \"the doc\" [x] x) (f 7)"), "7");
+    assert_eq!(
+        error("(defn f \"the doc\" [x] x) (f)"),
+        EvalError::Arity {
+            name: "#<fn f>".to_string(),
+            got: 0
+        }
+    );
+}
+
+#[test]
+fn passing_assert_returns_nil() {
+    assert_eq!(show("(assert (= 1 1))"), "nil");
+    assert_eq!(show("(assert true \"never shown\")"), "nil");
+}
+
+#[test]
+fn failing_assert_carries_the_message_and_form() {
+    let err = error("(assert (= 1 2) \"one is not two\")");
+    assert_eq!(
+        err,
+        EvalError::Assertion {
+            message: Some("one is not two".to_string()),
+            form: "(= 1 2)".to_string()
+        }
+    );
+    assert_eq!(err.to_string(), "assert failed: one is not two\n(= 1 2)");
+}
+
+#[test]
+fn asserts_can_be_turned_off() {
+    let mut env = ENV::new();
+    env.assertions = false;
+    assert_eq!(eval_in("(assert false)", &mut env), Ok(Atom::Nil));
+}
+
+#[test]
+fn case_returns_the_matching_result() {
+    assert_eq!(show("(case 2 1 \"one\" 2 \"two\" \"other\")"), "\"two\"");
+    assert_eq!(show("(case (+ 1 2) (1 2) :low (3 4) :high)"), ":high");
+    assert_eq!(show("(case :b :a 1 :b 2)"), "2");
+}
+
+#[test]
+fn case_tests_arent_evaluated() {
+    assert_eq!(show("(def x 1) (case 'x x :symbol 1 :one)"), ":symbol");
+}
+
+#[test]
+fn case_falls_back_to_the_default() {
+    assert_eq!(show("(case 5 1 \"one\" 2 \"two\" \"other\")"), "\"other\"");
+    assert_eq!(
+        error("(case 5 1 \"one\")"),
+        EvalError::NoMatchingClause("5".to_string())
+    );
+}
+
+#[test]
+fn duplicate_case_constants_are_errors() {
+    assert_eq!(
+        error("(case 1 1 :a 1 :b)").to_string(),
+        "case: duplicate test constant: 1"
+    );
+    assert_eq!(
+        error("(case 1 (1 2) :a 2 :b)").to_string(),
+        "case: duplicate test constant: 2"
+    );
+}
+
+#[test]
+fn while_runs_until_the_condition_is_falsey() {
+    assert_eq!(
+        show("(def n (atom 0)) (def runs (atom 0)) (while (< @n 5) (swap! runs inc) (swap! n inc)) [@n @runs]"),
+        "[5 5]"
+    );
+    assert_eq!(
+        show("(def n (atom 0)) (while (< @n 3) (swap! n inc))"),
+        "nil"
+    );
+}
+
+#[test]
+fn while_with_a_false_condition_never_runs_the_body() {
+    assert_eq!(output("(while false (print \"ran\"))"), "");
+    assert_eq!(output("(while nil (print \"ran\"))"), "");
+}
+
+#[test]
+fn with_out_str_returns_what_the_body_printed() {
+    assert_eq!(show("(with-out-str (print \"hi\"))"), "\"hi\"");
+    assert_eq!(
+        show("(with-out-str (println 1) (print :a [2]))"),
+        "\"1\\n:a [2]\""
+    );
+    assert_eq!(show("(with-out-str)"), "\"\"");
+}
+
+#[test]
+fn with_out_str_puts_the_old_output_back() {
+    assert_eq!(
+        output("(print \"a\") (def s (with-out-str (print \"b\"))) (print \"c\" s)"),
+        "ac b"
+    );
+    assert_eq!(
+        output("(print (with-out-str (print 1 (with-out-str (print 2)))))"),
+        "1 2"
+    );
+}
+
+#[test]
+fn declare_allows_mutual_recursion() {
+    assert_eq!(
+        show(
+            "(declare my-even? my-odd?)
+             (defn my-even? [n] (if (= n 0) true (my-odd? (- n 1))))
+             (defn my-odd? [n] (if (= n 0) false (my-even? (- n 1))))
+             [(my-even? 10) (my-odd? 7) (my-even? 3)]"
+        ),
+        "[true true false]"
+    );
+}
+
+#[test]
+fn using_a_declared_name_before_its_def_is_an_error() {
+    assert_eq!(
+        error("(declare f) (defn g [] (f)) (g)"),
+        EvalError::Undefined("f".to_string())
+    );
+    assert_eq!(
+        error("(declare x) x").to_string(),
+        "x is declared but not defined yet"
+    );
+}
+
+#[test]
+fn declare_doesnt_replace_an_existing_definition() {
+    assert_eq!(show("(def x 1) (declare x) x"), "1");
+}
+
+#[test]
+fn eval_located_says_which_form_failed() {
+    let tokens = warp::AST::tokenize("(def x 1)\n 'a (inc :k)".to_string()).unwrap();
+    let ast = warp::AST::read(&tokens).unwrap();
+    let err = warp::eval_located(&ast, &mut ENV::new()).unwrap_err();
+    assert_eq!(err.position(), Some((2, 5)));
+    assert_eq!(err.message(), "expected number, got keyword");
+    assert_eq!(err.to_string(), "2:5: expected number, got keyword");
+}
+
+#[test]
+fn let_bindings_see_earlier_ones() {
+    assert_eq!(show("(let [a 1 b (+ a 1)] b)"), "2");
+    assert_eq!(
+        show("(let [a 1 b (+ a 1) c (* a b 10)] [a b c])"),
+        "[1 2 20]"
+    );
+}
+
+#[test]
+fn later_let_bindings_shadow_earlier_ones() {
+    assert_eq!(show("(let [a 1 a (+ a 10)] a)"), "11");
+    assert_eq!(
+        show("(def a :global) (let [a 1 b a a :inner] [a b])"),
+        "[:inner 1]"
+    );
+    assert_eq!(show("(def a :global) (let [a 1] a) a"), ":global");
+}
+
+#[test]
+fn if_let_binds_a_truthy_value() {
+    assert_eq!(show("(if-let [x (get-in {:a 1} [:a])] (+ x 1) :none)"), "2");
+    assert_eq!(
+        show("(if-let [x (get-in {:a 1} [:b])] (+ x 1) :none)"),
+        ":none"
+    );
+    assert_eq!(show("(if-let [x false] x)"), "nil");
+}
+
+#[test]
+fn when_let_runs_the_body_for_a_truthy_value() {
+    assert_eq!(output("(when-let [x 5] (print x) (print \"!\"))"), "5!");
+    assert_eq!(show("(when-let [x 5] (print x) (* x 2))"), "10");
+    assert_eq!(output("(when-let [x nil] (print \"ran\"))"), "");
+    assert_eq!(show("(when-let [x nil] 1)"), "nil");
+}
+
+fn defining(src: &str, env: &mut ENV) -> (Atom, Vec<String>) {
+    let tokens = warp::AST::tokenize(src.to_string()).unwrap();
+    warp::eval_defining(&warp::AST::read(&tokens).unwrap(), env).unwrap()
+}
+
+#[test]
+fn eval_defining_reports_new_definitions() {
+    let mut env = ENV::new();
+    let (value, defined) = defining("(def x 1)", &mut env);
+    assert_eq!(value, Atom::Symbol("x".to_string()));
+    assert_eq!(defined, ["x"]);
+
+    let (_, defined) = defining("(defn f [] 1) (def y 2) (def x 3) 4", &mut env);
+    assert_eq!(defined, ["f", "y", "x"]);
+}
+
+#[test]
+fn eval_defining_of_no_defs() {
+    let (value, defined) = defining("(+ 1 2)", &mut ENV::new());
+    assert_eq!(value, Atom::Int(3));
+    assert!(defined.is_empty());
+}
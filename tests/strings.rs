@@ -0,0 +1,123 @@
+mod common;
+
+use common::{error, eval_str, output, show};
+use warp::{eval, Atom, EvalError, AST, ENV};
+
+#[test]
+fn format_ints_strings_and_floats() {
+    assert_eq!(show("(format \"%d + %d = %d\" 1 2 3)"), "\"1 + 2 = 3\"");
+    assert_eq!(show("(format \"hi %s\" \"bob\")"), "\"hi bob\"");
+    assert_eq!(show("(format \"%f\" 1.5)"), "\"1.500000\"");
+    assert_eq!(show("(format \"%.2f\" 2)"), "\"2.00\"");
+}
+
+#[test]
+fn format_literal_percent() {
+    assert_eq!(show("(format \"100%%\")"), "\"100%\"");
+}
+
+#[test]
+fn format_argument_count_must_match() {
+    assert!(matches!(
+        error("(format \"%d %d\" 1)"),
+        EvalError::Malformed(_)
+    ));
+    assert!(matches!(
+        error("(format \"%d\" 1 2)"),
+        EvalError::Malformed(_)
+    ));
+}
+
+#[test]
+fn split_on_a_separator() {
+    assert_eq!(show("(str/split \"a,b,c\" \",\")"), "[\"a\" \"b\" \"c\"]");
+    assert_eq!(show("(str/split \"a,,b\" \",\")"), "[\"a\" \"\" \"b\"]");
+    assert_eq!(show("(str/split \"abc\" \",\")"), "[\"abc\"]");
+}
+
+#[test]
+fn split_on_nothing_gives_the_characters() {
+    assert_eq!(show("(str/split \"abc\" \"\")"), "[\"a\" \"b\" \"c\"]");
+    assert_eq!(show("(str/split \"héé\" \"\")"), "[\"h\" \"é\" \"é\"]");
+}
+
+#[test]
+fn join_with_a_separator() {
+    assert_eq!(show("(str/join \",\" [\"a\" \"b\"])"), "\"a,b\"");
+    assert_eq!(show("(str/join \", \" '(1 :k))"), "\"1, :k\"");
+    assert_eq!(show("(str/join \",\" [])"), "\"\"");
+}
+
+#[test]
+fn split_then_join_round_trips() {
+    assert_eq!(
+        show("(str/join \",\" (str/split \"a,b,,c\" \",\"))"),
+        "\"a,b,,c\""
+    );
+}
+
+#[test]
+fn count_is_characters_and_byte_count_is_bytes() {
+    assert_eq!(show("(count \"héllo\")"), "5");
+    assert_eq!(show("(byte-count \"héllo\")"), "6");
+    assert_eq!(show("(count \"日本\")"), "2");
+    assert_eq!(show("(byte-count \"日本\")"), "6");
+}
+
+#[test]
+fn subs_works_on_characters() {
+    assert_eq!(show("(subs \"héllo\" 1 3)"), "\"él\"");
+    assert_eq!(show("(subs \"héllo\" 2)"), "\"llo\"");
+    assert_eq!(show("(subs \"héllo\" 5)"), "\"\"");
+    assert!(matches!(
+        error("(subs \"héllo\" 2 6)"),
+        EvalError::IndexOutOfBounds { index: 6, count: 5 }
+    ));
+}
+
+#[test]
+fn equal_string_literals_share_one_allocation() {
+    let tokens = warp::AST::tokenize("[\"héllo\" \"héllo\" \"other\"]".to_string()).unwrap();
+    let ast = warp::AST::read(&tokens).unwrap();
+    match ast.form(&ast.roots[0]) {
+        warp::Atom::Vector(items) => match items.as_slice() {
+            [warp::Atom::String(a), warp::Atom::String(b), warp::Atom::String(c)] => {
+                assert!(std::rc::Rc::ptr_eq(a, b));
+                assert!(!std::rc::Rc::ptr_eq(a, c));
+            }
+            other => panic!("expected three strings, got {:?}", other),
+        },
+        other => panic!("expected a vector, got {}", other),
+    }
+}
+
+#[test]
+fn pr_str_quotes_and_escapes_strings() {
+    assert_eq!(show(r#"(pr-str "a\nb")"#), r#""\"a\\nb\"""#);
+    assert_eq!(
+        show(r#"(pr-str "q\"" :k 1/2 nil)"#),
+        r#""\"q\\\"\" :k 1/2 nil""#
+    );
+    assert_eq!(show(r"(pr-str \x \newline)"), r#""\\x \\newline""#);
+    assert_eq!(show("(pr-str)"), "\"\"");
+}
+
+#[test]
+fn pr_str_output_reads_back_as_the_same_value() {
+    let src = r#"["a\tb\\" \space {:k #{1}} :x/y 2.5 nil]"#;
+    let printed = match eval_str(&format!("(pr-str {})", src)).unwrap() {
+        Atom::String(s) => s.to_string(),
+        other => panic!("pr-str gave {}", other),
+    };
+    let tokens = AST::tokenize(printed).unwrap();
+    let read = eval(&AST::read(&tokens).unwrap(), &mut ENV::new()).unwrap();
+    assert_eq!(read, eval_str(src).unwrap());
+}
+
+#[test]
+fn pr_and_prn_write_readably_while_print_does_not() {
+    assert_eq!(
+        output(r#"(pr "a" \b) (prn [1 "c"]) (print "d" \e)"#),
+        "\"a\" \\b[1 \"c\"]\nd e"
+    );
+}